@@ -0,0 +1,225 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Fields, GenericArgument, Ident, LitStr, PathArguments, Token, Type};
+
+#[derive(Default)]
+struct Attrs {
+    keyword : Option<LitStr>,
+    sep : Option<LitStr>,
+    delimited : Option<(LitStr, LitStr)>,
+    primitive : Option<Ident>,
+}
+
+impl Attrs {
+    fn from(attrs : &[Attribute]) -> Result<Attrs, Error> {
+        let mut result = Attrs::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("parse")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("keyword") {
+                    result.keyword = Some(meta.value()?.parse()?);
+                }
+                else if meta.path.is_ident("sep") {
+                    result.sep = Some(meta.value()?.parse()?);
+                }
+                else if meta.path.is_ident("delimited") {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    let open = content.parse()?;
+                    content.parse::<Token![,]>()?;
+                    let close = content.parse()?;
+                    result.delimited = Some((open, close));
+                }
+                else if meta.path.is_ident("symbol") || meta.path.is_ident("number") || meta.path.is_ident("string") {
+                    result.primitive = meta.path.get_ident().cloned();
+                }
+                else {
+                    return Err(meta.error("expected keyword, sep, delimited, symbol, number or string"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(result)
+    }
+}
+
+// `Vec<T>` gives `Some(("Vec", T))`
+fn generic_arg(ty : &Type) -> Option<(String, &Type)> {
+    let path = match ty {
+        Type::Path(p) if p.qself.is_none() => &p.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match args.args.first() {
+            Some(GenericArgument::Type(inner)) => Some((segment.ident.to_string(), inner)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// an expression that coerces to `fn(&mut Input) -> Result<T, ParseError>`
+fn item_parser(ty : &Type, attrs : &Attrs) -> TokenStream {
+    match &attrs.primitive {
+        Some(p) => {
+            let method = format_ident!("parse_{}", p);
+            quote! { |i| i.#method() }
+        },
+        None => quote! { <#ty as ::parse_input::Parse>::parse },
+    }
+}
+
+fn item_call(ty : &Type, attrs : &Attrs) -> TokenStream {
+    match &attrs.primitive {
+        Some(p) => {
+            let method = format_ident!("parse_{}", p);
+            quote! { input.#method()? }
+        },
+        None => quote! { <#ty as ::parse_input::Parse>::parse(input)? },
+    }
+}
+
+fn field_parser(ty : &Type, attrs : &Attrs) -> TokenStream {
+    let value = match generic_arg(ty) {
+        // the keyword and delimiters are part of what is optional
+        Some((ref name, inner)) if name == "Option" => {
+            let value = field_parser(inner, attrs);
+            return quote! { input.maybe(|input| ::core::result::Result::Ok(#value))? };
+        },
+        Some((ref name, inner)) if name == "Box" => {
+            let value = field_parser(inner, attrs);
+            return quote! { ::std::boxed::Box::new(#value) };
+        },
+        Some((ref name, inner)) if name == "Vec" => {
+            let item = item_parser(inner, attrs);
+            let sep = attrs.sep.clone().unwrap_or_else(|| LitStr::new(",", proc_macro2::Span::call_site()));
+            match &attrs.delimited {
+                Some((open, close)) => quote! { input.delimited_list(#open, #sep, #close, #item)? },
+                None if attrs.sep.is_some() => quote! { input.sep_by(#sep, #item)? },
+                None => quote! { input.list(#item)? },
+            }
+        },
+        _ => match &attrs.delimited {
+            Some((open, close)) => {
+                let item = item_parser(ty, attrs);
+                quote! { input.between(#open, #item, #close)? }
+            },
+            None => item_call(ty, attrs),
+        },
+    };
+    let keyword = attrs.keyword.iter();
+    quote! {
+        {
+            #( input.expect(#keyword)?; )*
+            #value
+        }
+    }
+}
+
+// parses the fields of a struct or variant and builds `path`
+fn fields_parser(path : TokenStream, fields : &Fields, attrs : &Attrs) -> Result<TokenStream, Error> {
+    let mut lets = vec![];
+    let mut names = vec![];
+    for (index, field) in fields.iter().enumerate() {
+        let field_attrs = Attrs::from(&field.attrs)?;
+        if index > 0 {
+            if let Some(sep) = &attrs.sep {
+                lets.push(quote! { input.expect(#sep)?; });
+            }
+        }
+        let name = format_ident!("field_{}", index);
+        let value = field_parser(&field.ty, &field_attrs);
+        lets.push(quote! { let #name = #value; });
+        names.push(name);
+    }
+
+    let build = match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|f| &f.ident);
+            quote! { #path { #( #idents: #names ),* } }
+        },
+        Fields::Unnamed(_) => quote! { #path ( #( #names ),* ) },
+        Fields::Unit => quote! { #path },
+    };
+
+    let keyword = attrs.keyword.iter();
+    let (open, close) = match &attrs.delimited {
+        Some((open, close)) => (Some(open), Some(close)),
+        None => (None, None),
+    };
+    let (open, close) = (open.iter(), close.iter());
+    Ok(quote! {
+        #( input.expect(#keyword)?; )*
+        #( input.expect(#open)?; )*
+        #( #lets )*
+        #( input.expect(#close)?; )*
+        ::core::result::Result::Ok(#build)
+    })
+}
+
+fn derive(input : DeriveInput) -> Result<TokenStream, Error> {
+    let attrs = Attrs::from(&input.attrs)?;
+    let name = &input.ident;
+
+    let body = match &input.data {
+        Data::Struct(data) => fields_parser(quote! { #name }, &data.fields, &attrs)?,
+        Data::Enum(data) => {
+            if data.variants.is_empty() {
+                return Err(Error::new_spanned(name, "Parse can't be derived for an enum without variants"));
+            }
+            let mut alternatives = vec![];
+            for variant in data.variants.iter() {
+                let variant_attrs = Attrs::from(&variant.attrs)?;
+                if variant.fields.is_empty() && variant_attrs.keyword.is_none() {
+                    return Err(Error::new_spanned(variant, "unit variants need #[parse(keyword = \"...\")]"));
+                }
+                let ident = &variant.ident;
+                let parse = fields_parser(quote! { #name::#ident }, &variant.fields, &variant_attrs)?;
+                alternatives.push(quote! { |input| { #parse } });
+            }
+            let keyword = attrs.keyword.iter();
+            let (open, close) = match &attrs.delimited {
+                Some((open, close)) => (Some(open), Some(close)),
+                None => (None, None),
+            };
+            let (open, close) = (open.iter(), close.iter());
+            quote! {
+                #( input.expect(#keyword)?; )*
+                #( input.expect(#open)?; )*
+                let value = input.choice(&[ #( #alternatives ),* ])?;
+                #( input.expect(#close)?; )*
+                ::core::result::Result::Ok(value)
+            }
+        },
+        Data::Union(_) => return Err(Error::new_spanned(name, "Parse can't be derived for unions")),
+    };
+
+    let mut generics = input.generics.clone();
+    let params = generics.type_params().map(|p| p.ident.clone()).collect::<Vec<_>>();
+    for param in params {
+        generics.make_where_clause().predicates.push(parse_quote! { #param : ::parse_input::Parse });
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::parse_input::Parse for #name #ty_generics #where_clause {
+            fn parse(input : &mut ::parse_input::Input) -> ::core::result::Result<Self, ::parse_input::ParseError> {
+                #body
+            }
+        }
+    })
+}
+
+/// Derives `parse_input::Parse`.  Fields are parsed in order with their own `Parse`
+/// implementation, or with `parse_symbol`, `parse_number` or `parse_string` when marked
+/// `#[parse(symbol)]`, `#[parse(number)]` or `#[parse(string)]`.  `Vec` fields use `list`
+/// and `Option` fields use `maybe`.  Enum variants are tried in order with `choice`.
+///
+/// * `#[parse(keyword = "fn")]` expects the text before the type, variant or field
+/// * `#[parse(sep = ";")]` separates the items of a `Vec` field, or the fields of a type or variant
+/// * `#[parse(delimited("(", ")"))]` surrounds the type, variant or field
+#[proc_macro_derive(Parse, attributes(parse))]
+pub fn derive_parse(input : proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive(input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
@@ -0,0 +1,239 @@
+use super::{Input, PSym, ParseError};
+
+/// What `parse_csv` does with records that have a different number of fields than the
+/// first one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RaggedRows {
+    Error,
+    Allow,
+    /// Short records get empty fields at the end, long records are still an error.
+    Pad,
+}
+
+/// Describes a CSV dialect for `Input::parse_csv`.
+#[derive(Debug, Clone, Copy)]
+pub struct CsvSpec {
+    delimiter : char,
+    quote : char,
+    header : bool,
+    ragged : RaggedRows,
+}
+
+impl CsvSpec {
+    /// `,` for CSV or `\t` for TSV, with `"` quotes, no header and `RaggedRows::Error`.
+    pub fn new(delimiter : char) -> CsvSpec {
+        CsvSpec { delimiter, quote: '"', header: false, ragged: RaggedRows::Error }
+    }
+
+    pub fn quote(mut self, quote : char) -> CsvSpec {
+        self.quote = quote;
+        self
+    }
+
+    /// Treats the first record as column names.
+    pub fn header(mut self) -> CsvSpec {
+        self.header = true;
+        self
+    }
+
+    pub fn ragged(mut self, ragged : RaggedRows) -> CsvSpec {
+        self.ragged = ragged;
+        self
+    }
+}
+
+/// Fields span their quotes when quoted.  Empty unquoted fields have `start == end` at
+/// the position where they would have been.
+#[derive(Debug, Clone)]
+pub struct Csv {
+    pub header : Option<Vec<PSym>>,
+    pub rows : Vec<Vec<PSym>>,
+}
+
+impl Csv {
+    pub fn column(&self, name : &str) -> Option<usize> {
+        self.header.as_ref()?.iter().position(|h| h.value == name)
+    }
+
+    pub fn get(&self, row : usize, column : &str) -> Option<&PSym> {
+        self.rows.get(row)?.get(self.column(column)?)
+    }
+}
+
+impl<'a> Input<'a> {
+
+    /// Parses RFC 4180 records until the end of input.  Records end with `\n` or `\r\n`,
+    /// and quoted fields may contain the delimiter, line breaks and doubled quotes.
+    /// Whitespace is kept as part of the fields.  Errors give the 1-based row and column
+    /// of the field, counting the header as row 1.
+    pub fn parse_csv(&mut self, spec : CsvSpec) -> Result<Csv, ParseError> {
+        let mut records = vec![];
+        while !self.data.is_empty() {
+            let row = records.len() + 1;
+            let mut record = vec![field(self, &spec, row, 1)?];
+            let end = loop {
+                match self.data {
+                    [] => break self.position(),
+                    [(i, '\n'), rest @ ..] | [(i, '\r'), (_, '\n'), rest @ ..] => {
+                        self.data = rest;
+                        break *i;
+                    },
+                    [(_, c), rest @ ..] if *c == spec.delimiter => {
+                        self.data = rest;
+                        record.push(field(self, &spec, row, record.len() + 1)?);
+                    },
+                    _ => unreachable!("fields stop at a delimiter or line break"),
+                }
+            };
+
+            if let Some(first) = records.first().map(Vec::len) {
+                check_width(&mut record, first, row, end, spec.ragged)?;
+            }
+            records.push(record);
+        }
+
+        let header = if spec.header && !records.is_empty() { Some(records.remove(0)) } else { None };
+        Ok(Csv { header, rows: records })
+    }
+}
+
+fn at(row : usize, column : usize) -> String {
+    format!("at row {}, column {}", row, column)
+}
+
+// padding fields are placed at `end`, the line break after the record
+fn check_width(record : &mut Vec<PSym>, width : usize, row : usize, end : usize, ragged : RaggedRows) -> Result<(), ParseError> {
+    match ragged {
+        RaggedRows::Allow => Ok(()),
+        RaggedRows::Pad if record.len() < width => {
+            record.resize(width, PSym { start: end, end, value: String::new() });
+            Ok(())
+        },
+        _ if record.len() == width => Ok(()),
+        // the first extra field, or the end of a short record
+        _ if record.len() > width =>
+            Err(ParseError::ErrorAt(record[width].start, format!("Expected {} fields but found {} {}", width, record.len(), at(row, width + 1)))),
+        _ => Err(ParseError::ErrorAt(end, format!("Expected {} fields but found {} {}", width, record.len(), at(row, record.len() + 1)))),
+    }
+}
+
+fn field(input : &mut Input, spec : &CsvSpec, row : usize, column : usize) -> Result<PSym, ParseError> {
+    let start = input.position();
+    let is_end = |d : &[(usize, char)]| matches!( d, [] | [(_, '\n'), ..] | [(_, '\r'), (_, '\n'), ..] )
+                                     || matches!( d, [(_, c), ..] if *c == spec.delimiter );
+
+    match input.data {
+        [(_, q), rest @ ..] if *q == spec.quote => {
+            input.data = rest;
+            let mut value = String::new();
+            loop {
+                match input.data {
+                    [] => return Err(ParseError::EndOfFile(format!("Expected closing {} for field {}", spec.quote, at(row, column)))),
+                    [(_, a), (_, b), rest @ ..] if *a == spec.quote && *b == spec.quote => {
+                        input.data = rest;
+                        value.push(spec.quote);
+                    },
+                    [(end, q), rest @ ..] if *q == spec.quote => {
+                        let end = *end;
+                        input.data = rest;
+                        if !is_end(input.data) {
+                            let (i, c) = input.data[0];
+                            return Err(ParseError::ErrorAt(i, format!("Expected delimiter or end of line after closing {} but found {:?} {}", spec.quote, c, at(row, column))));
+                        }
+                        return Ok(PSym { start, end, value });
+                    },
+                    [(_, c), rest @ ..] => {
+                        input.data = rest;
+                        value.push(*c);
+                    },
+                }
+            }
+        },
+        _ => {
+            let mut end = start;
+            let mut value = String::new();
+            while !is_end(input.data) {
+                let (i, c) = input.data[0];
+                if c == spec.quote {
+                    return Err(ParseError::ErrorAt(i, format!("Encountered {} inside unquoted field {}", c, at(row, column))));
+                }
+                end = i;
+                value.push(c);
+                input.data = &input.data[1 ..];
+            }
+            Ok(PSym { start, end, value })
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s : &str, spec : CsvSpec) -> Result<Csv, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        input.parse_csv(spec)
+    }
+
+    fn values(row : &[PSym]) -> Vec<&str> {
+        row.iter().map(|f| f.value.as_str()).collect()
+    }
+
+    #[test]
+    fn should_parse_quoted_fields() -> Result<(), ParseError> {
+        let csv = parse("name,quote\r\n\"Smith, J\",\"He said \"\"hi\"\"\r\nthen left\"\r\n plain , \r\n", CsvSpec::new(',').header())?;
+        assert_eq!( values(csv.header.as_ref().unwrap()), vec!["name", "quote"] );
+        assert_eq!( csv.rows.len(), 2 );
+        assert_eq!( values(&csv.rows[0]), vec!["Smith, J", "He said \"hi\"\r\nthen left"] );
+        assert_eq!( values(&csv.rows[1]), vec![" plain ", " "] );
+        assert_eq!( csv.get(0, "quote").unwrap().start, 23 );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_tsv_with_other_quote() -> Result<(), ParseError> {
+        let csv = parse("a\t'b\tc'\t\n'it''s'\t\t", CsvSpec::new('\t').quote('\''))?;
+        assert_eq!( values(&csv.rows[0]), vec!["a", "b\tc", ""] );
+        assert_eq!( values(&csv.rows[1]), vec!["it's", "", ""] );
+        Ok(())
+    }
+
+    #[test]
+    fn should_give_spans() -> Result<(), ParseError> {
+        let csv = parse("ab,\"c\",\n", CsvSpec::new(','))?;
+        let spans = csv.rows[0].iter().map(|f| (f.start, f.end)).collect::<Vec<_>>();
+        assert_eq!( spans, vec![(0, 1), (3, 5), (7, 7)] );
+        Ok(())
+    }
+
+    #[test]
+    fn should_apply_ragged_policy() -> Result<(), ParseError> {
+        let text = "a,b,c\n1,2\n";
+        assert!( matches!( parse(text, CsvSpec::new(',')), Err(ParseError::ErrorAt(9, _)) ) );
+        assert_eq!( parse(text, CsvSpec::new(',').ragged(RaggedRows::Allow))?.rows[1].len(), 2 );
+
+        let padded = parse(text, CsvSpec::new(',').ragged(RaggedRows::Pad))?;
+        assert_eq!( values(&padded.rows[1]), vec!["1", "2", ""] );
+        assert_eq!( (padded.rows[1][2].start, padded.rows[1][2].end), (9, 9) );
+
+        match parse("a\n1,2,3\n", CsvSpec::new(',').ragged(RaggedRows::Pad)) {
+            Err(ParseError::ErrorAt(4, message)) => assert!( message.ends_with("at row 2, column 2") ),
+            other => panic!( "unexpected {:?}", other ),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_rows_and_columns() {
+        match parse("a,b\n\"x\"y,z\n", CsvSpec::new(',')) {
+            Err(ParseError::ErrorAt(7, message)) => assert!( message.ends_with("at row 2, column 1") ),
+            other => panic!( "unexpected {:?}", other ),
+        }
+        match parse("a,b\nc,d\"e\n", CsvSpec::new(',')) {
+            Err(ParseError::ErrorAt(7, message)) => assert!( message.ends_with("at row 2, column 2") ),
+            other => panic!( "unexpected {:?}", other ),
+        }
+        assert!( matches!( parse("a,\"open\n", CsvSpec::new(',')), Err(ParseError::EndOfFile(_)) ) );
+    }
+}
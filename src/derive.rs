@@ -0,0 +1,141 @@
+use super::{Input, PSym, ParseError};
+
+/// A type with a parser over `Input`.  Enable the `derive` feature for `#[derive(Parse)]`.
+pub trait Parse : Sized {
+    fn parse(input : &mut Input) -> Result<Self, ParseError>;
+}
+
+impl Parse for PSym {
+    fn parse(input : &mut Input) -> Result<PSym, ParseError> {
+        input.parse_symbol()
+    }
+}
+
+impl<T : Parse> Parse for Box<T> {
+    fn parse(input : &mut Input) -> Result<Box<T>, ParseError> {
+        Ok(Box::new(T::parse(input)?))
+    }
+}
+
+impl<T : Parse> Parse for Option<T> {
+    fn parse(input : &mut Input) -> Result<Option<T>, ParseError> {
+        input.maybe(T::parse)
+    }
+}
+
+impl<T : Parse> Parse for Vec<T> {
+    fn parse(input : &mut Input) -> Result<Vec<T>, ParseError> {
+        input.list(T::parse)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parse_input_derive::Parse;
+
+    #[derive(Debug, Parse)]
+    #[parse(keyword = "fn")]
+    struct Function {
+        name : PSym,
+        #[parse(delimited("(", ")"))]
+        params : Vec<Param>,
+        #[parse(keyword = "->")]
+        ret : Option<Type>,
+        #[parse(delimited("{", "}"), sep = ";")]
+        body : Vec<Expr>,
+    }
+
+    #[derive(Debug, Parse)]
+    #[parse(sep = ":")]
+    struct Param(PSym, Type);
+
+    #[derive(Debug, Parse)]
+    enum Type {
+        #[parse(keyword = "int")]
+        Int,
+        #[parse(delimited("[", "]"))]
+        Array(Box<Type>),
+        Named(PSym),
+    }
+
+    #[derive(Debug, Parse)]
+    enum Expr {
+        Call { name : PSym, #[parse(delimited("(", ")"))] args : Vec<Expr> },
+        Num(#[parse(number)] PSym),
+        Str(#[parse(string)] PSym),
+    }
+
+    #[derive(Debug, Parse)]
+    struct Numbers {
+        #[parse(number, sep = "|")]
+        values : Vec<PSym>,
+    }
+
+    // the generated code must not pick up names from the surrounding module
+    mod shadowed {
+        use crate::PSym;
+        use parse_input_derive::Parse;
+
+        #[allow(dead_code)]
+        type Result<T> = std::result::Result<T, String>;
+
+        #[derive(Debug, Parse)]
+        pub struct Pair {
+            pub a : PSym,
+            pub b : Option<Box<PSym>>,
+        }
+    }
+
+    fn parse<T : Parse>(s : &str) -> Result<T, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let v = T::parse(&mut input)?;
+        input.expect_end()?;
+        Ok(v)
+    }
+
+    #[test]
+    fn should_derive_struct_parser() -> Result<(), ParseError> {
+        let f = parse::<Function>(r#"fn add(a : int, b : [point]) -> int { print("x", 1); g() }"#)?;
+        assert_eq!( f.name.value, "add" );
+        assert_eq!( f.params.len(), 2 );
+        assert_eq!( f.params[1].0.value, "b" );
+        assert!( matches!( &f.params[1].1, Type::Array(t) if matches!( **t, Type::Named(ref n) if n.value == "point" ) ) );
+        assert!( matches!( f.ret, Some(Type::Int) ) );
+        assert_eq!( f.body.len(), 2 );
+        match &f.body[0] {
+            Expr::Call { name, args } => {
+                assert_eq!( name.value, "print" );
+                assert!( matches!( &args[..], [Expr::Str(s), Expr::Num(n)] if s.value == "x" && n.value == "1" ) );
+            },
+            e => panic!( "expected call but found {:?}", e ),
+        }
+
+        let f = parse::<Function>("fn main() {}")?;
+        assert!( f.params.is_empty() && f.ret.is_none() && f.body.is_empty() );
+        Ok(())
+    }
+
+    #[test]
+    fn should_derive_with_primitive_and_separator() -> Result<(), ParseError> {
+        let n = parse::<Numbers>("1 | 2.5 | -3")?;
+        assert_eq!( n.values.iter().map(|v| v.value.as_str()).collect::<Vec<_>>(), vec!["1", "2.5", "-3"] );
+        Ok(())
+    }
+
+    #[test]
+    fn should_derive_next_to_shadowed_names() -> Result<(), ParseError> {
+        let pair = parse::<shadowed::Pair>("a b")?;
+        assert_eq!( pair.a.value, "a" );
+        assert_eq!( pair.b.map(|b| b.value), Some("b".to_string()) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_derived_parser() {
+        assert!( parse::<Function>("fun main() {}").is_err() );
+        assert!( parse::<Function>("fn main(a int) {}").is_err() );
+        assert!( parse::<Type>("[int").is_err() );
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use super::{Assoc, Input, PSym, ParseError, Pratt};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+/// An arithmetic or boolean expression.  Numbers are never negative; `-1` is `Neg`
+/// applied to `1`, so `-2 ^ 2` is `-(2 ^ 2)`.
+#[derive(Debug, Clone)]
+pub enum Expression {
+    /// The number as written.
+    Number(PSym),
+    Bool(bool),
+    Variable(PSym),
+    Unary(UnaryOp, Box<Expression>),
+    Binary(BinaryOp, Box<Expression>, Box<Expression>),
+    /// `cond ? then : else`
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>),
+    Call(PSym, Vec<Expression>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scalar {
+    Number(f64),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone)]
+pub enum EvalError {
+    UnknownVariable(PSym),
+    UnknownFunction(PSym),
+    Arity { function : PSym, expected : usize, found : usize },
+    /// The operator or function and the kind of value it needed.
+    TypeMismatch(String, &'static str),
+    DivisionByZero,
+    /// A `Number` whose text isn't a number, which `parse_expression` never produces.
+    InvalidNumber(PSym),
+}
+
+impl BinaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Or => "||",
+            BinaryOp::And => "&&",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::Pow => "^",
+        }
+    }
+}
+
+impl std::fmt::Display for EvalError {
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EvalError::UnknownVariable(name) => write!(f, "Variable {} at {} is not bound", name.value, name.start),
+            EvalError::UnknownFunction(name) => write!(f, "Function {} at {} is not defined", name.value, name.start),
+            EvalError::Arity { function, expected, found } =>
+                write!(f, "Function {} at {} takes {} arguments but was given {}", function.value, function.start, expected, found),
+            EvalError::TypeMismatch(op, kind) => write!(f, "{} expects {}", op, kind),
+            EvalError::DivisionByZero => write!(f, "Division by zero"),
+            EvalError::InvalidNumber(n) => write!(f, "Number {} at {} is not valid", n.value, n.start),
+        }
+    }
+}
+
+fn parser() -> &'static Pratt<Expression> {
+    static PARSER : OnceLock<Pratt<Expression>> = OnceLock::new();
+
+    fn binary(op : BinaryOp, l : Expression, r : Expression) -> Expression {
+        Expression::Binary(op, Box::new(l), Box::new(r))
+    }
+
+    PARSER.get_or_init(|| Pratt::new(atom)
+        .ternary("?", ":", 1, |c, t, e| Expression::Conditional(Box::new(c), Box::new(t), Box::new(e)))
+        .infix("||", 2, Assoc::Left, |l, r| binary(BinaryOp::Or, l, r))
+        .infix("&&", 3, Assoc::Left, |l, r| binary(BinaryOp::And, l, r))
+        .infix("==", 4, Assoc::NonAssoc, |l, r| binary(BinaryOp::Eq, l, r))
+        .infix("!=", 4, Assoc::NonAssoc, |l, r| binary(BinaryOp::Ne, l, r))
+        .infix("<", 5, Assoc::NonAssoc, |l, r| binary(BinaryOp::Lt, l, r))
+        .infix("<=", 5, Assoc::NonAssoc, |l, r| binary(BinaryOp::Le, l, r))
+        .infix(">", 5, Assoc::NonAssoc, |l, r| binary(BinaryOp::Gt, l, r))
+        .infix(">=", 5, Assoc::NonAssoc, |l, r| binary(BinaryOp::Ge, l, r))
+        .infix("+", 6, Assoc::Left, |l, r| binary(BinaryOp::Add, l, r))
+        .infix("-", 6, Assoc::Left, |l, r| binary(BinaryOp::Sub, l, r))
+        .infix("*", 7, Assoc::Left, |l, r| binary(BinaryOp::Mul, l, r))
+        .infix("/", 7, Assoc::Left, |l, r| binary(BinaryOp::Div, l, r))
+        .infix("%", 7, Assoc::Left, |l, r| binary(BinaryOp::Rem, l, r))
+        .prefix("-", 8, |e| Expression::Unary(UnaryOp::Neg, Box::new(e)))
+        .prefix("!", 8, |e| Expression::Unary(UnaryOp::Not, Box::new(e)))
+        .infix("^", 9, Assoc::Right, |l, r| binary(BinaryOp::Pow, l, r))
+        .group("(", ")"))
+}
+
+// the prefix `-` is tried before this, so numbers here never have a sign
+fn atom(input : &mut Input) -> Result<Expression, ParseError> {
+    input.clear()?;
+    match input.data {
+        [(_, c), ..] if c.is_ascii_digit() => Ok(Expression::Number(input.parse_number()?)),
+        [(_, c), ..] if c.is_alphabetic() || *c == '_' => {
+            let name = input.parse_symbol()?;
+            match name.value.as_str() {
+                "true" => Ok(Expression::Bool(true)),
+                "false" => Ok(Expression::Bool(false)),
+                _ if input.peek_char()? == Some('(') => {
+                    let args = input.delimited_list("(", ",", ")", |i| i.parse_expression())?;
+                    Ok(Expression::Call(name, args))
+                },
+                _ => Ok(Expression::Variable(name)),
+            }
+        },
+        _ => Err(input.unexpected("expression")),
+    }
+}
+
+impl<'a> Input<'a> {
+
+    /// Parses an expression with numbers, `true`, `false`, variables, function calls,
+    /// parentheses and, from lowest to highest precedence, `? :`, `||`, `&&`, `==` `!=`,
+    /// `<` `<=` `>` `>=`, `+` `-`, `*` `/` `%`, prefix `-` `!` and right associative `^`.
+    pub fn parse_expression(&mut self) -> Result<Expression, ParseError> {
+        parser().parse(self)
+    }
+}
+
+fn number(op : &str, value : Scalar) -> Result<f64, EvalError> {
+    match value {
+        Scalar::Number(n) => Ok(n),
+        Scalar::Bool(_) => Err(EvalError::TypeMismatch(op.to_string(), "a number")),
+    }
+}
+
+fn boolean(op : &str, value : Scalar) -> Result<bool, EvalError> {
+    match value {
+        Scalar::Bool(b) => Ok(b),
+        Scalar::Number(_) => Err(EvalError::TypeMismatch(op.to_string(), "a boolean")),
+    }
+}
+
+fn call(name : &PSym, args : Vec<Scalar>) -> Result<Scalar, EvalError> {
+    let arity = |expected : usize| if args.len() == expected {
+        Ok(())
+    }
+    else {
+        Err(EvalError::Arity { function: name.clone(), expected, found: args.len() })
+    };
+    let unary = |f : fn(f64) -> f64| {
+        arity(1)?;
+        Ok(Scalar::Number(f(number(&name.value, args[0])?)))
+    };
+
+    match name.value.as_str() {
+        "abs" => unary(f64::abs),
+        "sqrt" => unary(f64::sqrt),
+        "floor" => unary(f64::floor),
+        "ceil" => unary(f64::ceil),
+        "round" => unary(f64::round),
+        "min" | "max" => {
+            if args.is_empty() {
+                return Err(EvalError::Arity { function: name.clone(), expected: 1, found: 0 });
+            }
+            let mut result = number(&name.value, args[0])?;
+            for arg in &args[1 ..] {
+                let n = number(&name.value, *arg)?;
+                result = if name.value == "min" { result.min(n) } else { result.max(n) };
+            }
+            Ok(Scalar::Number(result))
+        },
+        _ => Err(EvalError::UnknownFunction(name.clone())),
+    }
+}
+
+impl Expression {
+
+    /// Evaluates the expression with `bindings` for its variables.  `&&`, `||` and `? :`
+    /// only evaluate the operands they need.  The functions are `abs`, `sqrt`, `floor`,
+    /// `ceil`, `round`, and `min` and `max` of one or more numbers.
+    pub fn eval(&self, bindings : &HashMap<String, Scalar>) -> Result<Scalar, EvalError> {
+        match self {
+            Expression::Number(n) => n.value.parse().map(Scalar::Number).map_err(|_| EvalError::InvalidNumber(n.clone())),
+            Expression::Bool(b) => Ok(Scalar::Bool(*b)),
+            Expression::Variable(name) => bindings.get(&name.value).copied().ok_or_else(|| EvalError::UnknownVariable(name.clone())),
+            Expression::Unary(UnaryOp::Neg, e) => Ok(Scalar::Number(-number("-", e.eval(bindings)?)?)),
+            Expression::Unary(UnaryOp::Not, e) => Ok(Scalar::Bool(!boolean("!", e.eval(bindings)?)?)),
+            Expression::Binary(BinaryOp::And, l, r) =>
+                Ok(Scalar::Bool(boolean("&&", l.eval(bindings)?)? && boolean("&&", r.eval(bindings)?)?)),
+            Expression::Binary(BinaryOp::Or, l, r) =>
+                Ok(Scalar::Bool(boolean("||", l.eval(bindings)?)? || boolean("||", r.eval(bindings)?)?)),
+            Expression::Binary(BinaryOp::Eq, l, r) => Ok(Scalar::Bool(l.eval(bindings)? == r.eval(bindings)?)),
+            Expression::Binary(BinaryOp::Ne, l, r) => Ok(Scalar::Bool(l.eval(bindings)? != r.eval(bindings)?)),
+            Expression::Binary(op, l, r) => {
+                let (l, r) = (number(op.symbol(), l.eval(bindings)?)?, number(op.symbol(), r.eval(bindings)?)?);
+                Ok(match op {
+                    BinaryOp::Lt => Scalar::Bool(l < r),
+                    BinaryOp::Le => Scalar::Bool(l <= r),
+                    BinaryOp::Gt => Scalar::Bool(l > r),
+                    BinaryOp::Ge => Scalar::Bool(l >= r),
+                    BinaryOp::Add => Scalar::Number(l + r),
+                    BinaryOp::Sub => Scalar::Number(l - r),
+                    BinaryOp::Mul => Scalar::Number(l * r),
+                    BinaryOp::Div | BinaryOp::Rem if r == 0.0 => return Err(EvalError::DivisionByZero),
+                    BinaryOp::Div => Scalar::Number(l / r),
+                    BinaryOp::Rem => Scalar::Number(l % r),
+                    BinaryOp::Pow => Scalar::Number(l.powf(r)),
+                    BinaryOp::And | BinaryOp::Or | BinaryOp::Eq | BinaryOp::Ne => unreachable!("handled above"),
+                })
+            },
+            Expression::Conditional(c, t, e) =>
+                if boolean("?:", c.eval(bindings)?)? { t.eval(bindings) } else { e.eval(bindings) },
+            Expression::Call(name, args) => {
+                let args = args.iter().map(|a| a.eval(bindings)).collect::<Result<Vec<_>, _>>()?;
+                call(name, args)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s : &str) -> Result<Expression, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let e = input.parse_expression()?;
+        input.expect_end()?;
+        Ok(e)
+    }
+
+    fn eval(s : &str) -> Result<Scalar, EvalError> {
+        let mut bindings = HashMap::new();
+        bindings.insert("x".to_string(), Scalar::Number(3.0));
+        bindings.insert("flag".to_string(), Scalar::Bool(true));
+        parse(s).unwrap().eval(&bindings)
+    }
+
+    // prints the tree back with every operation in parentheses
+    fn show(e : &Expression) -> String {
+        match e {
+            Expression::Number(n) => n.value.clone(),
+            Expression::Bool(b) => b.to_string(),
+            Expression::Variable(v) => v.value.clone(),
+            Expression::Unary(op, e) => format!("({:?} {})", op, show(e)),
+            Expression::Binary(op, l, r) => format!("({} {:?} {})", show(l), op, show(r)),
+            Expression::Conditional(c, t, e) => format!("({} ? {} : {})", show(c), show(t), show(e)),
+            Expression::Call(f, args) => format!("{}({})", f.value, args.iter().map(show).collect::<Vec<_>>().join(", ")),
+        }
+    }
+
+    #[test]
+    fn should_parse_precedence() -> Result<(), ParseError> {
+        assert_eq!( show(&parse("1 + 2 * 3 ^ 2 ^ 1")?), "(1 Add (2 Mul (3 Pow (2 Pow 1))))" );
+        assert_eq!( show(&parse("a < b && !c || d == (e - 1)")?), "(((a Lt b) And (Not c)) Or (d Eq (e Sub 1)))" );
+        assert_eq!( show(&parse("c ? max(1, x - 2) : f()")?), "(c ? max(1, (x Sub 2)) : f())" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_keep_unary_minus_apart_from_literals() -> Result<(), ParseError> {
+        assert_eq!( show(&parse("-2 ^ 2")?), "(Neg (2 Pow 2))" );
+        assert_eq!( show(&parse("1 -2")?), "(1 Sub 2)" );
+        assert_eq!( show(&parse("x--1")?), "(x Sub (Neg 1))" );
+        assert_eq!( eval("-2 ^ 2").unwrap(), Scalar::Number(-4.0) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_evaluate() {
+        assert_eq!( eval("x * (2 + 1.5) - 10 % 4").unwrap(), Scalar::Number(8.5) );
+        assert_eq!( eval("min(x, 7, -1) + abs(-x) + sqrt(16)").unwrap(), Scalar::Number(6.0) );
+        assert_eq!( eval("x >= 3 && flag ? 1 : 2").unwrap(), Scalar::Number(1.0) );
+        assert_eq!( eval("false && missing").unwrap(), Scalar::Bool(false) );
+        assert_eq!( eval("true != flag").unwrap(), Scalar::Bool(false) );
+    }
+
+    #[test]
+    fn should_report_eval_errors() {
+        assert!( matches!( eval("y + 1"), Err(EvalError::UnknownVariable(PSym { start: 0, .. })) ) );
+        assert!( matches!( eval("1 + nope(2)"), Err(EvalError::UnknownFunction(PSym { start: 4, .. })) ) );
+        assert!( matches!( eval("abs(1, 2)"), Err(EvalError::Arity { expected: 1, found: 2, .. }) ) );
+        assert!( matches!( eval("flag + 1"), Err(EvalError::TypeMismatch(op, "a number")) if op == "+" ) );
+        assert!( matches!( eval("1 / (x - 3)"), Err(EvalError::DivisionByZero) ) );
+
+        let bad = Expression::Number(PSym { start: 2, end: 3, value: "x".to_string() });
+        assert!( matches!( bad.eval(&HashMap::new()), Err(EvalError::InvalidNumber(PSym { start: 2, .. })) ) );
+    }
+
+    #[test]
+    fn should_report_parse_errors() {
+        assert!( matches!( parse("1 + * 2"), Err(ParseError::ErrorAt(4, _)) ) );
+        assert!( matches!( parse("f(1,"), Err(ParseError::EndOfFile(_)) ) );
+        assert!( matches!( parse("1 < 2 < 3"), Err(ParseError::ErrorAt(6, _)) ) );
+        assert!( matches!( parse(&format!("{}1", "-".repeat(20_000))), Err(ParseError::DepthExceeded(_)) ) );
+    }
+}
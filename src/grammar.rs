@@ -0,0 +1,179 @@
+/// Generates parser functions over `Input` from PEG style rules.
+///
+/// Each rule is `name : Type = alternative | alternative ;` and each alternative is a
+/// sequence of items followed by `=> action`.  Items may be bound with `name:item` for use
+/// in the action.  Items are
+///
+/// * `"text"` which expects the text
+/// * `symbol`, `number` and `string` which use the matching `parse_*` primitive
+/// * `list(item)` which parses a comma separated list
+/// * the name of another rule
+///
+/// and any item may be followed by `?`, `*` or `+` for `maybe`, `zero_or_more` and
+/// `one_or_more`.  Alternatives are tried in order with `choice`.  Actions run until the
+/// next top level `|` or `;`, so closures in actions need to be wrapped in parentheses.
+///
+/// ```
+/// use parse_input::{grammar, Input, PSym};
+///
+/// grammar! {
+///     pair : (String, Vec<PSym>) = key:symbol ":" values:list(number) => (key.value, values);
+/// }
+///
+/// let data = "x: 1, 2".char_indices().collect::<Vec<(usize, char)>>();
+/// let mut input = Input::new(&data);
+/// let (key, values) = pair(&mut input).unwrap();
+/// assert_eq!( key, "x" );
+/// assert_eq!( values.len(), 2 );
+/// ```
+#[macro_export]
+macro_rules! grammar {
+    () => {};
+
+    // sequence of items for the current alternative
+    (@seq $i:ident; $hdr:tt; $alts:tt; $items:tt; => $($rest:tt)*) => {
+        $crate::grammar!(@action $i; $hdr; $alts; $items; []; $($rest)*);
+    };
+    (@seq $i:ident; $hdr:tt; $alts:tt; $items:tt; $b:ident : $($rest:tt)*) => {
+        $crate::grammar!(@atom $i; $hdr; $alts; $items; $b; $($rest)*);
+    };
+    (@seq $i:ident; $hdr:tt; $alts:tt; $items:tt; $($rest:tt)*) => {
+        $crate::grammar!(@atom $i; $hdr; $alts; $items; _; $($rest)*);
+    };
+
+    (@atom $i:ident; $hdr:tt; $alts:tt; $items:tt; $b:tt; list ( $($inner:tt)* ) $($rest:tt)*) => {
+        $crate::grammar!(@postfix $i; $hdr; $alts; $items; $b; (list ( $($inner)* )); $($rest)*);
+    };
+    (@atom $i:ident; $hdr:tt; $alts:tt; $items:tt; $b:tt; $l:literal $($rest:tt)*) => {
+        $crate::grammar!(@postfix $i; $hdr; $alts; $items; $b; ($l); $($rest)*);
+    };
+    (@atom $i:ident; $hdr:tt; $alts:tt; $items:tt; $b:tt; $r:ident $($rest:tt)*) => {
+        $crate::grammar!(@postfix $i; $hdr; $alts; $items; $b; ($r); $($rest)*);
+    };
+
+    (@postfix $i:ident; $hdr:tt; $alts:tt; [$($items:tt)*]; $b:tt; ($($atom:tt)*); ? $($rest:tt)*) => {
+        $crate::grammar!(@seq $i; $hdr; $alts; [$($items)* { $b ; $i.maybe(|$i| $crate::grammar!(@expr $i; $($atom)*)) }]; $($rest)*);
+    };
+    (@postfix $i:ident; $hdr:tt; $alts:tt; [$($items:tt)*]; $b:tt; ($($atom:tt)*); * $($rest:tt)*) => {
+        $crate::grammar!(@seq $i; $hdr; $alts; [$($items)* { $b ; $i.zero_or_more(|$i| $crate::grammar!(@expr $i; $($atom)*)) }]; $($rest)*);
+    };
+    (@postfix $i:ident; $hdr:tt; $alts:tt; [$($items:tt)*]; $b:tt; ($($atom:tt)*); + $($rest:tt)*) => {
+        $crate::grammar!(@seq $i; $hdr; $alts; [$($items)* { $b ; $i.one_or_more(|$i| $crate::grammar!(@expr $i; $($atom)*)) }]; $($rest)*);
+    };
+    (@postfix $i:ident; $hdr:tt; $alts:tt; [$($items:tt)*]; $b:tt; ($($atom:tt)*); $($rest:tt)*) => {
+        $crate::grammar!(@seq $i; $hdr; $alts; [$($items)* { $b ; $crate::grammar!(@expr $i; $($atom)*) }]; $($rest)*);
+    };
+
+    // action tokens run until the next `|` or `;`
+    (@action $i:ident; $hdr:tt; [$($alts:tt)*]; $items:tt; [$($act:tt)*]; | $($rest:tt)*) => {
+        $crate::grammar!(@seq $i; $hdr; [$($alts)* ($items [$($act)*])]; []; $($rest)*);
+    };
+    (@action $i:ident; $hdr:tt; [$($alts:tt)*]; $items:tt; [$($act:tt)*]; ; $($rest:tt)*) => {
+        $crate::grammar!(@emit $i; $hdr; [$($alts)* ($items [$($act)*])]);
+        $crate::grammar!($($rest)*);
+    };
+    (@action $i:ident; $hdr:tt; $alts:tt; $items:tt; [$($act:tt)*]; $t:tt $($rest:tt)*) => {
+        $crate::grammar!(@action $i; $hdr; $alts; $items; [$($act)* $t]; $($rest)*);
+    };
+
+    (@emit $i:ident; [$vis:vis $name:ident $t:ty]; [$( ([$({ $b:tt ; $($e:tt)* })*] [$($act:tt)*]) )*]) => {
+        #[allow(unused_variables)]
+        $vis fn $name($i : &mut $crate::Input) -> Result<$t, $crate::ParseError> {
+            $i.choice(&[ $( |$i : &mut $crate::Input| -> Result<$t, $crate::ParseError> {
+                                $( let $b = $($e)*?; )*
+                                Ok({ $($act)* })
+                            }
+                          ),*
+                       ])
+        }
+    };
+
+    (@expr $i:ident; list ( $($inner:tt)* )) => { $i.list(|$i| $crate::grammar!(@expr $i; $($inner)*)) };
+    (@expr $i:ident; symbol) => { $i.parse_symbol() };
+    (@expr $i:ident; number) => { $i.parse_number() };
+    (@expr $i:ident; string) => { $i.parse_string() };
+    (@expr $i:ident; $l:literal) => { $i.expect($l) };
+    (@expr $i:ident; $r:ident) => { $r($i) };
+
+    ($vis:vis $name:ident : $t:ty = $($rest:tt)*) => {
+        $crate::grammar!(@seq input; [$vis $name $t]; []; []; $($rest)*);
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Input, ParseError, PSym};
+
+    #[derive(Debug, PartialEq)]
+    enum Value {
+        Number(String),
+        Str(String),
+        Array(Vec<Value>),
+        Object(Vec<(String, Value)>),
+        Null,
+    }
+
+    grammar! {
+        value : Value = n:number => Value::Number(n.value)
+                      | s:string => Value::Str(s.value)
+                      | "[" items:list(value) "]" => Value::Array(items)
+                      | "{" fields:list(field) "}" => Value::Object(fields)
+                      | "null" => Value::Null
+                      ;
+
+        field : (String, Value) = key:string ":" v:value => (key.value, v);
+
+        pub(crate) path : Vec<PSym> = first:symbol rest:segment* => {
+            let mut path = vec![first];
+            path.extend(rest);
+            path
+        };
+
+        segment : PSym = "." s:symbol => s;
+
+        call : (PSym, Option<Vec<PSym>>) = f:symbol args:arguments? => (f, args);
+
+        arguments : Vec<PSym> = "(" args:symbol+ ")" => args;
+    }
+
+    fn parse<T>(s : &str, rule : fn(&mut Input) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let v = rule(&mut input)?;
+        input.expect_end()?;
+        Ok(v)
+    }
+
+    #[test]
+    fn should_parse_with_alternatives_and_lists() -> Result<(), ParseError> {
+        let v = parse(r#"{ "a": [1, "x", null], "b": {} }"#, value)?;
+        assert_eq!( v, Value::Object(vec![ ("a".to_string(), Value::Array(vec![ Value::Number("1".to_string())
+                                                                               , Value::Str("x".to_string())
+                                                                               , Value::Null
+                                                                               ]))
+                                         , ("b".to_string(), Value::Object(vec![]))
+                                         ]) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_with_repetition() -> Result<(), ParseError> {
+        let p = parse("a.b.c", path)?;
+        assert_eq!( p.into_iter().map(|s| s.value).collect::<Vec<_>>(), vec!["a", "b", "c"] );
+
+        let (f, args) = parse("f(x y)", call)?;
+        assert_eq!( f.value, "f" );
+        assert_eq!( args.unwrap().len(), 2 );
+
+        let (_, args) = parse("f", call)?;
+        assert!( args.is_none() );
+
+        assert!( parse("f()", call).is_err() );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_on_bad_input() {
+        assert!( parse(r#"{ "a" 1 }"#, value).is_err() );
+    }
+}
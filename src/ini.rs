@@ -0,0 +1,319 @@
+use super::{Input, PSym, ParseError};
+
+/// What `parse_ini` does when a key appears twice in the same section.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DuplicateKeys {
+    Error,
+    KeepFirst,
+    /// The later value replaces the earlier one where the earlier one was.
+    KeepLast,
+    KeepAll,
+}
+
+#[derive(Debug, Clone)]
+pub struct IniEntry {
+    pub key : PSym,
+    pub value : PSym,
+}
+
+#[derive(Debug, Clone)]
+pub struct IniSection {
+    /// `None` for the entries before the first section header.
+    pub name : Option<PSym>,
+    pub entries : Vec<IniEntry>,
+}
+
+/// Sections in the order they first appear.  Repeated section headers add to the
+/// earlier section.
+#[derive(Debug, Clone)]
+pub struct Ini {
+    pub sections : Vec<IniSection>,
+}
+
+impl IniSection {
+    /// The value of the last entry named `key`.
+    pub fn get(&self, key : &str) -> Option<&PSym> {
+        self.entries.iter().rev().find(|e| e.key.value == key).map(|e| &e.value)
+    }
+}
+
+impl Ini {
+    /// The entries before the first section header.
+    pub fn global(&self) -> &IniSection {
+        &self.sections[0]
+    }
+
+    pub fn section(&self, name : &str) -> Option<&IniSection> {
+        self.sections.iter().find(|s| s.name.as_ref().is_some_and(|n| n.value == name))
+    }
+
+    pub fn get(&self, section : &str, key : &str) -> Option<&PSym> {
+        self.section(section)?.get(key)
+    }
+}
+
+impl<'a> Input<'a> {
+
+    /// Parses an INI or Java properties file until the end of input.  Lines hold a
+    /// `[section]` header, a `key = value` or `key : value` entry, or a comment starting
+    /// with `;` or `#`.  Values may be quoted with `"` (using the escapes of
+    /// `parse_string`) or `'`, and an unquoted value ending in `\` continues on the next
+    /// line without its leading whitespace.  Comments can follow headers and quoted values on the same line.
+    pub fn parse_ini(&mut self, duplicates : DuplicateKeys) -> Result<Ini, ParseError> {
+        let mut ini = Ini { sections: vec![IniSection { name: None, entries: vec![] }] };
+        let mut current = 0;
+
+        loop {
+            skip_blanks(self);
+            match self.data {
+                [] => return Ok(ini),
+                [(_, '\n' | '\r'), rest @ ..] => self.data = rest,
+                [(_, ';' | '#'), ..] => end_of_line(self)?,
+                [(_, '['), ..] => {
+                    let name = section_header(self)?;
+                    current = match ini.sections.iter().position(|s| s.name.as_ref().is_some_and(|n| n.value == name.value)) {
+                        Some(index) => index,
+                        None => {
+                            ini.sections.push(IniSection { name: Some(name), entries: vec![] });
+                            ini.sections.len() - 1
+                        },
+                    };
+                },
+                _ => {
+                    let entry = entry(self)?;
+                    add(&mut ini.sections[current], entry, duplicates)?;
+                },
+            }
+        }
+    }
+}
+
+fn add(section : &mut IniSection, entry : IniEntry, duplicates : DuplicateKeys) -> Result<(), ParseError> {
+    let existing = section.entries.iter().position(|e| e.key.value == entry.key.value);
+    match (existing, duplicates) {
+        (Some(index), DuplicateKeys::Error) =>
+            Err(ParseError::ErrorAt(entry.key.start, format!("Duplicate key {} first defined at {}", entry.key.value, section.entries[index].key.start))),
+        (Some(_), DuplicateKeys::KeepFirst) => Ok(()),
+        (Some(index), DuplicateKeys::KeepLast) => {
+            section.entries[index] = entry;
+            Ok(())
+        },
+        _ => {
+            section.entries.push(entry);
+            Ok(())
+        },
+    }
+}
+
+fn skip_blanks(input : &mut Input) {
+    while let [(_, ' ' | '\t'), rest @ ..] = input.data {
+        input.data = rest;
+    }
+}
+
+// consumes trailing blanks, an optional comment and the line break
+fn end_of_line(input : &mut Input) -> Result<(), ParseError> {
+    skip_blanks(input);
+    if let [(_, ';' | '#'), ..] = input.data {
+        while let [(_, c), rest @ ..] = input.data {
+            if *c == '\n' {
+                break;
+            }
+            input.data = rest;
+        }
+    }
+    match input.data {
+        [] => Ok(()),
+        [(_, '\n'), rest @ ..] | [(_, '\r'), (_, '\n'), rest @ ..] => {
+            input.data = rest;
+            Ok(())
+        },
+        _ => Err(input.unexpected("end of line")),
+    }
+}
+
+// the characters of the current line up to one of `stops`
+fn take_until<'a>(input : &mut Input<'a>, stops : &[char]) -> &'a [(usize, char)] {
+    let d = input.data;
+    let len = d.iter().take_while(|(_, c)| *c != '\n' && !stops.contains(c)).count();
+    input.data = &d[len ..];
+    &d[.. len]
+}
+
+fn trim(mut cs : &[(usize, char)]) -> &[(usize, char)] {
+    while let [(_, c), rest @ ..] = cs {
+        if !c.is_whitespace() {
+            break;
+        }
+        cs = rest;
+    }
+    while let [rest @ .., (_, c)] = cs {
+        if !c.is_whitespace() {
+            break;
+        }
+        cs = rest;
+    }
+    cs
+}
+
+fn to_sym(cs : &[(usize, char)], position : usize) -> PSym {
+    match cs {
+        [] => PSym { start: position, end: position, value: String::new() },
+        [(start, _), .., (end, _)] => PSym { start: *start, end: *end, value: cs.iter().map(|(_, c)| c).collect() },
+        [(start, c)] => PSym { start: *start, end: *start, value: c.to_string() },
+    }
+}
+
+fn section_header(input : &mut Input) -> Result<PSym, ParseError> {
+    let open = input.position();
+    input.raw_expect("[")?;
+    let name = trim(take_until(input, &[']']));
+    match input.data {
+        [(_, ']'), rest @ ..] => input.data = rest,
+        _ => return Err(input.unexpected("`]`")),
+    }
+    if name.is_empty() {
+        return Err(ParseError::ErrorAt(open, "Expected section name in []".to_string()));
+    }
+    end_of_line(input)?;
+    Ok(to_sym(name, open))
+}
+
+fn entry(input : &mut Input) -> Result<IniEntry, ParseError> {
+    let key_start = input.position();
+    let key = trim(take_until(input, &['=', ':']));
+    let key = match input.data {
+        [(i, sep), rest @ ..] if *sep == '=' || *sep == ':' => {
+            if key.is_empty() {
+                return Err(ParseError::ErrorAt(*i, format!("Expected key before {}", sep)));
+            }
+            input.data = rest;
+            to_sym(key, key_start)
+        },
+        _ => {
+            let key = to_sym(key, key_start);
+            return Err(ParseError::ErrorAt(key.start, format!("Expected = or : after key {}", key.value)));
+        },
+    };
+
+    skip_blanks(input);
+    let value = match input.data {
+        [(_, '"'), ..] => {
+            let value = input.parse_string()?;
+            end_of_line(input)?;
+            value
+        },
+        [(start, '\''), rest @ ..] => {
+            input.data = rest;
+            let value = take_until(input, &['\'']);
+            let close = match input.data {
+                [(i, '\''), rest @ ..] => {
+                    input.data = rest;
+                    *i
+                },
+                _ => return Err(input.unexpected("`'`")),
+            };
+            end_of_line(input)?;
+            // the span covers the quotes, as with `parse_string`
+            PSym { start: *start, end: close, value: value.iter().map(|(_, c)| c).collect() }
+        },
+        _ => unquoted_value(input)?,
+    };
+
+    Ok(IniEntry { key, value })
+}
+
+fn unquoted_value(input : &mut Input) -> Result<PSym, ParseError> {
+    let start = input.position();
+    let mut cs = vec![];
+    loop {
+        let line = trim(take_until(input, &[]));
+        match line {
+            [rest @ .., (_, '\\')] => {
+                cs.extend_from_slice(rest);
+                match input.data {
+                    [(_, '\n'), rest @ ..] => input.data = rest,
+                    _ => return Err(input.unexpected("line after `\\`")),
+                }
+                skip_blanks(input);
+            },
+            _ => {
+                cs.extend_from_slice(line);
+                end_of_line(input)?;
+                return Ok(to_sym(&cs, start));
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s : &str, duplicates : DuplicateKeys) -> Result<Ini, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        input.parse_ini(duplicates)
+    }
+
+    #[test]
+    fn should_parse_ini() -> Result<(), ParseError> {
+        let ini = parse("name = top\r\n; comment\n[server]  # main\nhost: example.com\n  port = 8080 \nempty =\n\n[paths]\nquoted = \"a\\tb ; c\" ; note\nsingle = ' x '\n[server]\nuser=admin", DuplicateKeys::Error)?;
+        assert_eq!( ini.global().get("name").unwrap().value, "top" );
+        assert_eq!( ini.sections.len(), 3 );
+        assert_eq!( ini.get("server", "host").unwrap().value, "example.com" );
+        assert_eq!( ini.get("server", "port").unwrap().value, "8080" );
+        assert_eq!( ini.get("server", "empty").unwrap().value, "" );
+        assert_eq!( ini.get("server", "user").unwrap().value, "admin" );
+        assert_eq!( ini.get("paths", "quoted").unwrap().value, "a\tb ; c" );
+        assert_eq!( ini.get("paths", "single").unwrap().value, " x " );
+        Ok(())
+    }
+
+    #[test]
+    fn should_give_spans() -> Result<(), ParseError> {
+        let ini = parse("[s]\nkey = some value\n", DuplicateKeys::Error)?;
+        let section = ini.section("s").unwrap();
+        assert_eq!( (section.name.as_ref().unwrap().start, section.name.as_ref().unwrap().end), (1, 1) );
+        let entry = &section.entries[0];
+        assert_eq!( (entry.key.start, entry.key.end), (4, 6) );
+        assert_eq!( (entry.value.start, entry.value.end), (10, 19) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_join_continued_lines() -> Result<(), ParseError> {
+        let ini = parse("list = a, \\\n       b, \\\n       c\nnext = 1", DuplicateKeys::Error)?;
+        assert_eq!( ini.global().get("list").unwrap().value, "a, b, c" );
+        assert_eq!( ini.global().get("next").unwrap().value, "1" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_apply_duplicate_policy() -> Result<(), ParseError> {
+        let text = "a = 1\nb = 2\na = 3\n";
+        assert!( matches!( parse(text, DuplicateKeys::Error), Err(ParseError::ErrorAt(12, _)) ) );
+
+        let first = parse(text, DuplicateKeys::KeepFirst)?;
+        assert_eq!( first.global().get("a").unwrap().value, "1" );
+
+        let last = parse(text, DuplicateKeys::KeepLast)?;
+        let keys = last.global().entries.iter().map(|e| format!("{}={}", e.key.value, e.value.value)).collect::<Vec<_>>();
+        assert_eq!( keys, vec!["a=3", "b=2"] );
+
+        let all = parse(text, DuplicateKeys::KeepAll)?;
+        assert_eq!( all.global().entries.len(), 3 );
+        assert_eq!( all.global().get("a").unwrap().value, "3" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_errors() {
+        assert!( matches!( parse("[open\nx = 1", DuplicateKeys::Error), Err(ParseError::ErrorAt(5, _)) ) );
+        assert!( matches!( parse("[]", DuplicateKeys::Error), Err(ParseError::ErrorAt(0, _)) ) );
+        assert!( matches!( parse("novalue\n", DuplicateKeys::Error), Err(ParseError::ErrorAt(0, _)) ) );
+        assert!( matches!( parse(" = 1", DuplicateKeys::Error), Err(ParseError::ErrorAt(1, _)) ) );
+        assert!( matches!( parse("k = \"x\" y", DuplicateKeys::Error), Err(ParseError::ErrorAt(8, _)) ) );
+        assert!( matches!( parse("k = 'x", DuplicateKeys::Error), Err(ParseError::EndOfFile(_)) ) );
+    }
+}
@@ -0,0 +1,340 @@
+use super::{Input, ListSpec, NumberFormat, PSym, ParseError, StringFormat, StringSpec, TrailingSeparator, Whitespace};
+
+/// Which dialect `parse_json` accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JsonMode {
+    /// RFC 8259 only.
+    Strict,
+    /// Adds the JSONC and JSON5 extensions: `//` and `/* */` comments, trailing commas,
+    /// unquoted keys, single quoted strings, hex numbers, and `Infinity` and `NaN` with an
+    /// optional sign.
+    Relaxed,
+}
+
+#[derive(Debug, Clone)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    /// The number as written, which keeps precision that `f64` would lose.  In relaxed
+    /// mode it may also be hex or `Infinity` or `NaN`, each with a sign.
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in the order written.  Duplicate keys are kept.
+    Object(Vec<(PSym, Json)>),
+}
+
+/// A JSON value with the byte indices of its first and last characters.
+#[derive(Debug, Clone)]
+pub struct Json {
+    pub start : usize,
+    pub end : usize,
+    pub value : JsonValue,
+}
+
+impl Json {
+    /// The value of the last member named `key` when this is an object.
+    pub fn get(&self, key : &str) -> Option<&Json> {
+        match &self.value {
+            JsonValue::Object(members) => members.iter().rev().find(|(k, _)| k.value == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match &self.value {
+            JsonValue::Number(n) => {
+                let (sign, digits) = match n.strip_prefix('-') {
+                    Some(digits) => (-1.0, digits),
+                    None => (1.0, n.strip_prefix('+').unwrap_or(n)),
+                };
+                match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+                    Some(hex) => u64::from_str_radix(hex, 16).ok().map(|h| sign * h as f64),
+                    None => digits.parse::<f64>().ok().map(|f| sign * f),
+                }
+            },
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match &self.value {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Input<'a> {
+
+    /// Parses a JSON value along with any whitespace around it.  In strict mode only JSON
+    /// whitespace is skipped, so comments are errors.  In relaxed mode whitespace and
+    /// comments are skipped with `clear`, along with `//` line comments.  Arrays and objects
+    /// nested deeper than the depth limit, `DEFAULT_MAX_DEPTH` unless changed with
+    /// `with_max_depth`, fail with `DepthExceeded` instead of overflowing the stack.
+    pub fn parse_json(&mut self, mode : JsonMode) -> Result<Json, ParseError> {
+        let whitespace = self.whitespace;
+        self.whitespace = match mode {
+            JsonMode::Strict => Whitespace::Json,
+            JsonMode::Relaxed => Whitespace::LineComments,
+        };
+        let result = value(self, mode).and_then(|value| {
+            self.clear()?;
+            Ok(value)
+        });
+        self.whitespace = whitespace;
+        result
+    }
+
+    /// `parse_json` followed by the end of input.
+    pub fn parse_json_document(&mut self, mode : JsonMode) -> Result<Json, ParseError> {
+        let value = self.parse_json(mode)?;
+        match self.data {
+            [] => Ok(value),
+            _ => Err(self.unexpected("end of input")),
+        }
+    }
+}
+
+// `separated` takes plain functions, so each mode gets its own entry points
+fn strict_value(input : &mut Input) -> Result<Json, ParseError> {
+    value(input, JsonMode::Strict)
+}
+
+fn relaxed_value(input : &mut Input) -> Result<Json, ParseError> {
+    value(input, JsonMode::Relaxed)
+}
+
+fn strict_member(input : &mut Input) -> Result<(PSym, Json), ParseError> {
+    member(input, JsonMode::Strict)
+}
+
+fn relaxed_member(input : &mut Input) -> Result<(PSym, Json), ParseError> {
+    member(input, JsonMode::Relaxed)
+}
+
+fn value(input : &mut Input, mode : JsonMode) -> Result<Json, ParseError> {
+    input.clear()?;
+    let relaxed = mode == JsonMode::Relaxed;
+    let start = input.position();
+    match input.data {
+        [(_, '{'), ..] => {
+            let members = match mode {
+                JsonMode::Strict => input.delimited_list("{", ",", "}", strict_member)?,
+                JsonMode::Relaxed => input.separated(&relaxed_list("{", "}"), relaxed_member)?,
+            };
+            Ok(Json { start, end: last(input), value: JsonValue::Object(members) })
+        },
+        [(_, '['), ..] => {
+            let items = match mode {
+                JsonMode::Strict => input.delimited_list("[", ",", "]", strict_value)?,
+                JsonMode::Relaxed => input.separated(&relaxed_list("[", "]"), relaxed_value)?,
+            };
+            Ok(Json { start, end: last(input), value: JsonValue::Array(items) })
+        },
+        [(_, q @ ('"' | '\'')), ..] if *q == '"' || relaxed => {
+            let s = input.parse_string_with(&StringSpec::new().quote(*q).format(StringFormat::Json))?;
+            Ok(Json { start: s.start, end: s.end, value: JsonValue::String(s.value) })
+        },
+        [(_, c), ..] if *c == '-' || c.is_ascii_digit() || relaxed && "+IN".contains(*c) => {
+            let n = if relaxed { relaxed_number(input)? } else { input.parse_number_with(NumberFormat::Json)? };
+            Ok(Json { start: n.start, end: n.end, value: JsonValue::Number(n.value) })
+        },
+        [(_, 't'), ..] => literal(input, "true", JsonValue::Bool(true)),
+        [(_, 'f'), ..] => literal(input, "false", JsonValue::Bool(false)),
+        [(_, 'n'), ..] => literal(input, "null", JsonValue::Null),
+        _ => Err(input.unexpected("JSON value")),
+    }
+}
+
+fn relaxed_list<'s>(open : &'s str, close : &'s str) -> ListSpec<'s> {
+    ListSpec::new(",").delimited(open, close).trailing(TrailingSeparator::Allow)
+}
+
+// the index of the character just read
+fn last(input : &Input) -> usize {
+    input.source[input.offset() - 1].0
+}
+
+fn literal(input : &mut Input, word : &str, value : JsonValue) -> Result<Json, ParseError> {
+    let start = input.position();
+    input.raw_expect(word)?;
+    Ok(Json { start, end: start + word.len() - 1, value })
+}
+
+fn member(input : &mut Input, mode : JsonMode) -> Result<(PSym, Json), ParseError> {
+    input.clear()?;
+    let key = match input.data {
+        [(_, '\''), ..] if mode == JsonMode::Relaxed => input.parse_string_with(&StringSpec::new().quote('\'').format(StringFormat::Json))?,
+        [(_, '"'), ..] => input.parse_string_with(&StringSpec::new().format(StringFormat::Json))?,
+        _ if mode == JsonMode::Relaxed => input.parse_symbol()?,
+        _ => return Err(input.unexpected("string")),
+    };
+    input.expect(":")?;
+    Ok((key, value(input, mode)?))
+}
+
+// [+-]?(Infinity|NaN|0[xX][0-9a-fA-F]+), or a JSON number with an optional leading `+`
+fn relaxed_number(input : &mut Input) -> Result<PSym, ParseError> {
+    let start = input.position();
+    let rp = input.create_restore();
+    let mut value = String::new();
+    if let [(_, sign @ ('+' | '-')), rest @ ..] = input.data {
+        input.data = rest;
+        value.push(*sign);
+    }
+
+    for word in ["Infinity", "NaN"].iter() {
+        if input.raw_expect(word).is_ok() {
+            value.push_str(word);
+            return Ok(PSym { start, end: start + value.len() - 1, value });
+        }
+    }
+
+    if let [(_, '0'), (_, x @ ('x' | 'X')), rest @ ..] = input.data {
+        input.data = rest;
+        value.push('0');
+        value.push(*x);
+        while let [(_, c), rest @ ..] = input.data {
+            if !c.is_ascii_hexdigit() {
+                break;
+            }
+            input.data = rest;
+            value.push(*c);
+        }
+        if value.ends_with(['x', 'X']) {
+            return Err(input.unexpected("hex digit"));
+        }
+        return Ok(PSym { start, end: start + value.len() - 1, value });
+    }
+
+    if value == "+" {
+        if !matches!( input.data, [(_, c), ..] if c.is_ascii_digit() ) {
+            return Err(input.unexpected("digit"));
+        }
+        let n = input.parse_number_with(NumberFormat::Json)?;
+        return Ok(PSym { start, end: n.end, value: format!("+{}", n.value) });
+    }
+
+    input.restore(rp);
+    input.parse_number_with(NumberFormat::Json)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s : &str) -> Result<Json, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        input.parse_json_document(JsonMode::Strict)
+    }
+
+    #[test]
+    fn should_parse_json_with_spans() -> Result<(), ParseError> {
+        let v = parse(r#" { "a" : [1, -2.5e+3, true], "b": null, "λ": "xé😀" } "#)?;
+        assert_eq!( (v.start, v.end), (1, 56) );
+
+        let a = v.get("a").unwrap();
+        assert_eq!( (a.start, a.end), (9, 26) );
+        let items = a.as_array().unwrap();
+        assert_eq!( items[1].as_f64(), Some(-2500.0) );
+        assert_eq!( (items[1].start, items[1].end), (13, 19) );
+        assert!( matches!( items[2].value, JsonValue::Bool(true) ) );
+
+        assert!( matches!( v.get("b").unwrap().value, JsonValue::Null ) );
+        assert_eq!( v.get("λ").unwrap().as_str(), Some("xé😀") );
+
+        match &v.value {
+            JsonValue::Object(members) => assert_eq!( (members[1].0.start, members[1].0.end), (29, 31) ),
+            _ => panic!( "expected object" ),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_keep_number_text() -> Result<(), ParseError> {
+        let v = parse("12345678901234567890.000")?;
+        assert!( matches!( v.value, JsonValue::Number(ref n) if n == "12345678901234567890.000" ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_json_errors() {
+        assert!( matches!( parse("[1, 2"), Err(ParseError::EndOfFile(_)) ) );
+        assert!( matches!( parse("[01]"), Err(ParseError::ErrorAt(2, _)) ) );
+        assert!( matches!( parse(r#"["\uDC00"]"#), Err(ParseError::ErrorAt(2, _)) ) );
+        assert!( matches!( parse(&"[".repeat(1000)), Err(ParseError::DepthExceeded(_)) ) );
+    }
+
+    fn parse_relaxed(s : &str) -> Result<Json, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        input.parse_json_document(JsonMode::Relaxed)
+    }
+
+    #[test]
+    fn should_parse_relaxed_json() -> Result<(), ParseError> {
+        let text = r#"
+            // settings
+            {
+                name: 'it\'s',   /* single quotes */
+                "list": [1, 2, /* three */ 3,],
+                mask: 0xFF,
+                limits: [+Infinity, -Infinity, NaN, +1],
+            }
+            // done"#;
+        let v = parse_relaxed(text)?;
+        assert_eq!( v.get("name").unwrap().as_str(), Some("it's") );
+        assert_eq!( v.get("list").unwrap().as_array().unwrap().len(), 3 );
+        assert_eq!( v.get("mask").unwrap().as_f64(), Some(255.0) );
+
+        let limits = v.get("limits").unwrap().as_array().unwrap();
+        assert_eq!( limits[0].as_f64(), Some(f64::INFINITY) );
+        assert_eq!( limits[1].as_f64(), Some(f64::NEG_INFINITY) );
+        assert!( limits[2].as_f64().unwrap().is_nan() );
+        assert_eq!( limits[3].as_f64(), Some(1.0) );
+
+        assert!( parse(text).is_err() );
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_in_relaxed_json() {
+        assert!( parse_relaxed("[1,,2]").is_err() );
+        assert!( parse_relaxed("[,]").is_err() );
+        assert!( parse_relaxed("[0x]").is_err() );
+        assert!( parse_relaxed("{a b: 1}").is_err() );
+        assert!( parse_relaxed("[1] /* unclosed").is_err() );
+    }
+
+    #[test]
+    fn should_pass_json_test_suite() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/json");
+        let mut paths = std::fs::read_dir(dir).unwrap().map(|e| e.unwrap().path()).collect::<Vec<_>>();
+        paths.sort();
+        assert!( !paths.is_empty() );
+
+        for path in paths {
+            let name = path.file_name().unwrap().to_str().unwrap().to_string();
+            // input that is not UTF-8 cannot become a `&str` and so is always rejected
+            let result = match String::from_utf8(std::fs::read(&path).unwrap()) {
+                Ok(text) => parse(&text).is_ok(),
+                Err(_) => false,
+            };
+            if name.starts_with("y_") {
+                assert!( result, "{} should be accepted", name );
+            }
+            else if name.starts_with("n_") {
+                assert!( !result, "{} should be rejected", name );
+            }
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use super::{Input, ParseError, RestorePoint, Token, TokenKind};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lexeme<K> {
+    pub kind : K,
+    pub start : usize,
+    pub end : usize,
+    pub value : String,
+}
+
+impl From<Lexeme<TokenKind>> for Token {
+    fn from(lexeme : Lexeme<TokenKind>) -> Token {
+        Token { kind: lexeme.kind, start: lexeme.start, end: lexeme.end, value: lexeme.value }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Matcher {
+    Literal(&'static str),
+    Class(fn(char) -> bool, fn(char) -> bool),
+    Symbol,
+    Number,
+    String,
+}
+
+impl Matcher {
+    fn can_start(&self, c : char) -> bool {
+        match self {
+            Matcher::Literal(s) => s.starts_with(c),
+            Matcher::Class(first, _) => first(c),
+            Matcher::Symbol => c.is_alphabetic() || c == '_',
+            Matcher::Number => c.is_ascii_digit() || c == '-',
+            Matcher::String => c == '"',
+        }
+    }
+}
+
+struct Rule<K> {
+    kind : K,
+    matcher : Matcher,
+}
+
+/// Builds a lexer from a list of token rules.  At each position the longest match wins,
+/// and ties go to whichever rule was added first.  Whitespace and comments are skipped
+/// with `Input::clear`.
+pub struct LexerBuilder<K> {
+    rules : Vec<Rule<K>>,
+}
+
+impl<K : Copy> Default for LexerBuilder<K> {
+    fn default() -> Self {
+        LexerBuilder::new()
+    }
+}
+
+impl<K : Copy> LexerBuilder<K> {
+    pub fn new() -> LexerBuilder<K> {
+        LexerBuilder { rules: vec![] }
+    }
+
+    pub fn literal(mut self, kind : K, s : &'static str) -> LexerBuilder<K> {
+        assert!( !s.is_empty(), "literal token must not be empty" );
+        self.rules.push(Rule { kind, matcher: Matcher::Literal(s) });
+        self
+    }
+
+    pub fn literals(mut self, kind : K, ss : &[&'static str]) -> LexerBuilder<K> {
+        for s in ss {
+            self = self.literal(kind, s);
+        }
+        self
+    }
+
+    /// A token made of one character matching `first` followed by any number of characters
+    /// matching `rest`.
+    pub fn class(mut self, kind : K, first : fn(char) -> bool, rest : fn(char) -> bool) -> LexerBuilder<K> {
+        self.rules.push(Rule { kind, matcher: Matcher::Class(first, rest) });
+        self
+    }
+
+    pub fn symbol(mut self, kind : K) -> LexerBuilder<K> {
+        self.rules.push(Rule { kind, matcher: Matcher::Symbol });
+        self
+    }
+
+    pub fn number(mut self, kind : K) -> LexerBuilder<K> {
+        self.rules.push(Rule { kind, matcher: Matcher::Number });
+        self
+    }
+
+    pub fn string(mut self, kind : K) -> LexerBuilder<K> {
+        self.rules.push(Rule { kind, matcher: Matcher::String });
+        self
+    }
+
+    pub fn build(self) -> GeneratedLexer<K> {
+        let mut ascii = vec![vec![]; 128];
+        let mut other = vec![];
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            for (c, candidates) in ascii.iter_mut().enumerate() {
+                if rule.matcher.can_start(c as u8 as char) {
+                    candidates.push(index);
+                }
+            }
+            // predicates can't be enumerated over all of unicode, so they are checked when lexing
+            match rule.matcher {
+                Matcher::Literal(s) if s.starts_with(|c : char| c.is_ascii()) => { },
+                Matcher::Number | Matcher::String => { },
+                _ => other.push(index),
+            }
+        }
+
+        GeneratedLexer { rules: self.rules, ascii, other }
+    }
+}
+
+pub struct GeneratedLexer<K> {
+    rules : Vec<Rule<K>>,
+    ascii : Vec<Vec<usize>>,
+    other : Vec<usize>,
+}
+
+impl<K : Copy> GeneratedLexer<K> {
+
+    pub fn tokenize(&self, input : &mut Input) -> Result<Vec<Lexeme<K>>, ParseError> {
+        let mut lexemes = vec![];
+
+        while !input.at_end()? {
+            lexemes.push(self.next_lexeme(input)?);
+        }
+
+        Ok(lexemes)
+    }
+
+    pub fn next_lexeme(&self, input : &mut Input) -> Result<Lexeme<K>, ParseError> {
+        input.clear()?;
+
+        let (start, c) = match input.data {
+            [] => return Err(ParseError::EndOfFile("tokenize".to_string())),
+            [(i, c), ..] => (*i, *c),
+        };
+
+        let candidates = if c.is_ascii() { &self.ascii[c as usize] } else { &self.other };
+
+        let rp = input.create_restore();
+        let mut best : Option<(usize, String, RestorePoint)> = None;
+        for index in candidates.iter().copied() {
+            let rule = &self.rules[index];
+            if !rule.matcher.can_start(c) {
+                continue;
+            }
+            if let Ok(value) = run(rule.matcher, input) {
+                if best.as_ref().is_none_or(|(_, _, end)| input.data.len() < end.data.len()) {
+                    best = Some((index, value, input.create_restore()));
+                }
+            }
+            input.restore(rp);
+        }
+
+        match best {
+            Some((index, value, end)) => {
+                let consumed = &rp.data[.. rp.data.len() - end.data.len()];
+                input.restore(end);
+                let end = consumed.last().map_or(start, |(i, _)| *i);
+                Ok(Lexeme { kind: self.rules[index].kind, start, end, value })
+            },
+            None => Err(ParseError::ErrorAt(start, format!("Encountered {} in tokenize", c))),
+        }
+    }
+}
+
+fn run(matcher : Matcher, input : &mut Input) -> Result<String, ParseError> {
+    match matcher {
+        Matcher::Literal(s) => {
+            input.raw_expect(s)?;
+            Ok(s.to_string())
+        },
+        Matcher::Class(first, rest) => {
+            let mut d = input.data;
+            let mut cs = vec![];
+            match d {
+                [(_, x), r @ ..] if first(*x) => {
+                    cs.push(*x);
+                    d = r;
+                },
+                _ => return Err(ParseError::EndOfFile("class".to_string())),
+            }
+            while let [(_, x), r @ ..] = d {
+                if !rest(*x) {
+                    break;
+                }
+                cs.push(*x);
+                d = r;
+            }
+            input.data = d;
+            Ok(cs.into_iter().collect())
+        },
+        Matcher::Symbol => Ok(input.parse_symbol()?.value),
+        Matcher::Number => Ok(input.parse_number()?.value),
+        Matcher::String => Ok(input.parse_string()?.value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum K {
+        If,
+        Ident,
+        Assign,
+        Eq,
+        Arrow,
+        Minus,
+        Num,
+        Str,
+        Color,
+    }
+
+    fn lexer() -> GeneratedLexer<K> {
+        LexerBuilder::new().literal(K::If, "if")
+                           .literal(K::Eq, "==")
+                           .literal(K::Assign, "=")
+                           .literal(K::Arrow, "->")
+                           .literal(K::Minus, "-")
+                           .class(K::Color, |c| c == '#', |c| c.is_ascii_hexdigit())
+                           .symbol(K::Ident)
+                           .number(K::Num)
+                           .string(K::Str)
+                           .build()
+    }
+
+    fn lex(s : &str) -> Result<Vec<Lexeme<K>>, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        lexer().tokenize(&mut input)
+    }
+
+    #[test]
+    fn should_prefer_longest_match_then_priority() -> Result<(), ParseError> {
+        let ls = lex("if iffy == = -> -1 - x")?;
+        let kinds = ls.iter().map(|l| l.kind).collect::<Vec<_>>();
+        assert_eq!( kinds, vec![K::If, K::Ident, K::Eq, K::Assign, K::Arrow, K::Num, K::Minus, K::Ident] );
+        assert_eq!( ls[1], Lexeme { kind: K::Ident, start: 3, end: 6, value: "iffy".to_string() } );
+        assert_eq!( ls[5].value, "-1" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_lex_classes_strings_and_unicode() -> Result<(), ParseError> {
+        let ls = lex("#fa0 \"a\\nb\" λx")?;
+        assert_eq!( ls[0], Lexeme { kind: K::Color, start: 0, end: 3, value: "#fa0".to_string() } );
+        assert_eq!( ls[1], Lexeme { kind: K::Str, start: 5, end: 10, value: "a\nb".to_string() } );
+        assert_eq!( ls[2], Lexeme { kind: K::Ident, start: 12, end: 14, value: "λx".to_string() } );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_on_unknown_character() {
+        let e = lex("x ? y");
+        assert!( matches!( e, Err(ParseError::ErrorAt(2, _)) ) );
+    }
+
+    #[test]
+    fn should_convert_to_tokens() -> Result<(), ParseError> {
+        let data = "let x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let lexer = LexerBuilder::new().literal(TokenKind::Keyword, "let").symbol(TokenKind::Symbol).build();
+        let tokens = lexer.tokenize(&mut input)?.into_iter().map(Token::from).collect::<Vec<_>>();
+        let mut input = crate::TokenInput::new(&tokens);
+        input.expect("let")?;
+        assert_eq!( input.parse_symbol()?.value, "x" );
+        Ok(())
+    }
+}
@@ -1,20 +1,139 @@
 
-#[derive(Debug)]
+#[cfg(test)]
+extern crate self as parse_input;
+
+mod csv;
+mod derive;
+mod expr;
+mod grammar;
+mod ini;
+mod json;
+mod lexgen;
+mod memo;
+mod peg;
+mod pratt;
+mod pretty;
+mod regex_syntax;
+mod sexpr;
+mod shell_words;
+mod token;
+mod toml;
+mod uri;
+
+pub use csv::{Csv, CsvSpec, RaggedRows};
+pub use derive::Parse;
+pub use expr::{BinaryOp, EvalError, Expression, Scalar, UnaryOp};
+#[cfg(feature = "derive")]
+pub use parse_input_derive::Parse;
+pub use ini::{DuplicateKeys, Ini, IniEntry, IniSection};
+pub use json::{Json, JsonMode, JsonValue};
+pub use lexgen::{GeneratedLexer, Lexeme, LexerBuilder};
+pub use memo::MemoStats;
+pub use peg::{Expr, Grammar, GrammarError, Rule, Tree};
+pub use pratt::{Assoc, Pratt};
+pub use pretty::{format_number, quote_string, Doc};
+pub use regex_syntax::{ClassItem, GroupKind, Regex, RegexNode};
+pub use sexpr::{QuoteKind, SExpr, SExprValue};
+pub use shell_words::{ShellWord, WordPart};
+pub use token::{Lexer, Token, TokenInput, TokenKind, TokenRestorePoint};
+pub use toml::{Toml, TomlDatetime, TomlValue};
+pub use uri::{percent_decode, Authority, HostKind, Uri};
+
+#[derive(Debug, Clone)]
 pub struct PSym {
     pub start : usize,
     pub end : usize,
     pub value : String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ParseError {
     EndOfFile(String),
     ErrorAt(usize, String),
+    Fatal(Box<ParseError>),
+    Context(String, Box<ParseError>),
+    DepthExceeded(usize),
+    FuelExhausted(usize),
+    NoProgress(usize),
 }
 
+impl ParseError {
+    /// Fatal errors come from inside a `cut` or from the depth, fuel and progress checks, 
+    /// and are not backtracked over by `maybe`, `choice` and the repetition combinators.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            ParseError::Fatal(_) => true,
+            ParseError::DepthExceeded(_) => true,
+            ParseError::FuelExhausted(_) => true,
+            ParseError::NoProgress(_) => true,
+            ParseError::Context(_, e) => e.is_fatal(),
+            _ => false,
+        }
+    }
+
+    /// Context labels from `Input::context`, innermost first.
+    pub fn contexts(&self) -> Vec<&str> {
+        let mut contexts = vec![];
+        let mut e = self;
+        loop {
+            match e {
+                ParseError::Fatal(inner) => e = inner,
+                ParseError::Context(label, inner) => {
+                    contexts.push(label.as_str());
+                    e = inner;
+                },
+                _ => break,
+            }
+        }
+        contexts.reverse();
+        contexts
+    }
+
+    /// The underlying error without any fatal or context wrappers.
+    pub fn root(&self) -> &ParseError {
+        match self {
+            ParseError::Fatal(e) => e.root(),
+            ParseError::Context(_, e) => e.root(),
+            e => e,
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.root() {
+            ParseError::EndOfFile(message) => write!(f, "End of file: {}", message)?,
+            ParseError::ErrorAt(i, message) => write!(f, "Error at {}: {}", i, message)?,
+            ParseError::DepthExceeded(i) => write!(f, "Error at {}: Exceeded maximum nesting depth", i)?,
+            ParseError::FuelExhausted(i) => write!(f, "Error at {}: Ran out of fuel", i)?,
+            ParseError::NoProgress(i) => write!(f, "Error at {}: Repeated parser succeeded without consuming input", i)?,
+            _ => unreachable!("root error is never a wrapper"),
+        }
+
+        let contexts = self.contexts();
+        if !contexts.is_empty() {
+            write!(f, " ({})", contexts.join(" → "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+
+/// How deeply combinators may nest unless `Input::with_max_depth` says otherwise.  Every
+/// recursive parser in this crate goes through the same check, so deeply nested input
+/// fails with `DepthExceeded` instead of overflowing the stack.
+pub const DEFAULT_MAX_DEPTH : usize = 256;
 
 pub struct Input<'a> {
-    data : &'a [(usize, char)] 
+    data : &'a [(usize, char)],
+    source : &'a [(usize, char)],
+    memo : memo::MemoTable<'a>,
+    depth : usize,
+    max_depth : usize,
+    fuel : Option<usize>,
+    whitespace : Whitespace,
 }
 
 #[derive(Clone, Copy)]
@@ -22,10 +141,284 @@ pub struct RestorePoint<'a> {
     data : &'a [(usize, char)] 
 }
 
+/// What `Input::clear` skips.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Whitespace {
+    /// Unicode whitespace and nested `/* */` comments.
+    Default,
+    /// `Default` along with `//` comments to the end of the line.
+    LineComments,
+    /// Only space and tab, so that line breaks stay significant as in TOML.
+    Blank,
+    /// Only space, tab, line feed and carriage return, as in JSON.
+    Json,
+}
+
+/// Which numbers `Input::parse_number_with` accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumberFormat {
+    /// `-?[0-9]+(\.[0-9]+)?([eE]-?[0-9]+)?`, as read by `parse_number`.
+    Default,
+    /// RFC 8259 numbers, which forbid leading zeros and allow `+` in the exponent.
+    Json,
+}
+
+/// Which escapes and characters `Input::parse_string_with` accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StringFormat {
+    /// `\\`, `\n`, `\r`, `\0`, `\t` and the quote, as read by `parse_string`.
+    Default,
+    /// RFC 8259 escapes, including `\u` with surrogate pairs, and no control characters.
+    Json,
+    /// TOML basic strings, with `\u` and `\U` scalar values and no control characters
+    /// other than tab.
+    Toml,
+    /// TOML literal strings, which are `Toml` without any escapes.
+    TomlLiteral,
+}
+
+impl StringFormat {
+    fn forbids(self, c : char) -> bool {
+        match self {
+            StringFormat::Default => false,
+            StringFormat::Json => c < '\u{20}',
+            StringFormat::Toml | StringFormat::TomlLiteral => (c < '\u{20}' && c != '\t') || c == '\u{7f}',
+        }
+    }
+}
+
+/// Describes a quoted string for `Input::parse_string_with`.
+#[derive(Debug, Clone, Copy)]
+pub struct StringSpec {
+    quote : char,
+    format : StringFormat,
+    multiline : bool,
+}
+
+impl StringSpec {
+    /// `"` quotes with the escapes of `parse_string`.
+    pub fn new() -> StringSpec {
+        StringSpec { quote: '"', format: StringFormat::Default, multiline: false }
+    }
+
+    /// The quote character, which may also be escaped.
+    pub fn quote(mut self, quote : char) -> StringSpec {
+        self.quote = quote;
+        self
+    }
+
+    pub fn format(mut self, format : StringFormat) -> StringSpec {
+        self.format = format;
+        self
+    }
+
+    /// Delimits the string with three quotes, as in TOML.  Line breaks are read as `\n`
+    /// and one right after the opening quotes is dropped, up to two quotes may come right
+    /// before the closing ones, and in `StringFormat::Toml` a `\` at the end of a line
+    /// drops the line break and the whitespace after it.
+    pub fn multiline(mut self) -> StringSpec {
+        self.multiline = true;
+        self
+    }
+}
+
+impl Default for StringSpec {
+    fn default() -> StringSpec {
+        StringSpec::new()
+    }
+}
+
+/// Describes the characters of a symbol for `Input::parse_symbol_with`.
+#[derive(Debug, Clone, Copy)]
+pub struct SymbolSpec {
+    start : fn(char) -> bool,
+    rest : fn(char) -> bool,
+}
+
+impl SymbolSpec {
+    /// Alphabetic or `_` followed by alphanumeric or `_`, as read by `parse_symbol`.
+    pub fn new() -> SymbolSpec {
+        SymbolSpec { start: |c| c.is_alphabetic() || c == '_', rest: |c| c.is_alphanumeric() || c == '_' }
+    }
+
+    pub fn start(mut self, start : fn(char) -> bool) -> SymbolSpec {
+        self.start = start;
+        self
+    }
+
+    pub fn rest(mut self, rest : fn(char) -> bool) -> SymbolSpec {
+        self.rest = rest;
+        self
+    }
+}
+
+impl Default for SymbolSpec {
+    fn default() -> SymbolSpec {
+        SymbolSpec::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrailingSeparator {
+    Forbid,
+    Allow,
+    Require,
+}
+
+/// Describes a separated list for `Input::separated`.
+#[derive(Debug, Clone, Copy)]
+pub struct ListSpec<'s> {
+    sep : &'s str,
+    delimiters : Option<(&'s str, &'s str)>,
+    trailing : TrailingSeparator,
+    min : usize,
+    max : Option<usize>,
+}
+
+impl<'s> ListSpec<'s> {
+    pub fn new(sep : &'s str) -> ListSpec<'s> {
+        ListSpec { sep, delimiters: None, trailing: TrailingSeparator::Forbid, min: 0, max: None }
+    }
+
+    pub fn delimited(mut self, open : &'s str, close : &'s str) -> ListSpec<'s> {
+        self.delimiters = Some((open, close));
+        self
+    }
+
+    pub fn trailing(mut self, trailing : TrailingSeparator) -> ListSpec<'s> {
+        self.trailing = trailing;
+        self
+    }
+
+    pub fn min(mut self, min : usize) -> ListSpec<'s> {
+        self.min = min;
+        self
+    }
+
+    pub fn max(mut self, max : usize) -> ListSpec<'s> {
+        self.max = Some(max);
+        self
+    }
+}
+
+fn error_at(data : &[(usize, char)], message : String) -> ParseError {
+    match data {
+        [] => ParseError::EndOfFile(message),
+        [(i, _), ..] => ParseError::ErrorAt(*i, message),
+    }
+}
+
+// whether only spaces and tabs come before the next line break
+fn ends_line(d : &[(usize, char)]) -> bool {
+    let blank = d.iter().take_while(|(_, x)| *x == ' ' || *x == '\t').count();
+    matches!( &d[blank ..], [(_, '\n'), ..] | [(_, '\r'), (_, '\n'), ..] )
+}
+
+// reads the character escaped by the `\\` at `start`
+fn string_escape(d : &mut &[(usize, char)], start : usize, spec : &StringSpec) -> Result<char, ParseError> {
+    let (i, x) = match *d {
+        [] => return Err(ParseError::EndOfFile("parse_string".to_string())),
+        [(i, x), rest @ ..] => {
+            *d = rest;
+            (*i, *x)
+        },
+    };
+
+    match x {
+        '\\' => Ok('\\'),
+        'n' => Ok('\n'),
+        'r' => Ok('\r'),
+        't' => Ok('\t'),
+        '"' => Ok('"'),
+        x if x == spec.quote => Ok(x),
+        '0' if spec.format == StringFormat::Default => Ok('\0'),
+        '/' if spec.format == StringFormat::Json => Ok('/'),
+        'b' if spec.format != StringFormat::Default => Ok('\u{8}'),
+        'f' if spec.format != StringFormat::Default => Ok('\u{c}'),
+        'u' if spec.format == StringFormat::Json => unicode_escape(d, start),
+        'u' if spec.format == StringFormat::Toml => scalar_escape(d, 4, start),
+        'U' if spec.format == StringFormat::Toml => scalar_escape(d, 8, start),
+        x => Err(ParseError::ErrorAt(i, format!("Encountered unknown escape character {}", x))),
+    }
+}
+
+fn hex_digits(d : &mut &[(usize, char)], count : usize) -> Result<u32, ParseError> {
+    let mut n = 0;
+    for _ in 0..count {
+        match *d {
+            [(_, x), rest @ ..] if x.is_ascii_hexdigit() => {
+                *d = rest;
+                n = n * 16 + x.to_digit(16).unwrap();
+            },
+            _ => return Err(error_at(d, "Expected hex digit in parse_string".to_string())),
+        }
+    }
+    Ok(n)
+}
+
+// the `\\u` or `\\U` at `start` has been consumed; surrogates are not scalar values
+fn scalar_escape(d : &mut &[(usize, char)], count : usize, start : usize) -> Result<char, ParseError> {
+    let code = hex_digits(d, count)?;
+    char::from_u32(code).ok_or_else(|| ParseError::ErrorAt(start, format!("Encountered invalid unicode scalar value {:X}", code)))
+}
+
+// the `\\u` at `start` has been consumed; surrogate pairs are combined and lone surrogates
+// rejected
+fn unicode_escape(d : &mut &[(usize, char)], start : usize) -> Result<char, ParseError> {
+    let code = match hex_digits(d, 4)? {
+        high @ 0xD800..=0xDBFF => {
+            let low_start = match *d {
+                [(i, '\\'), (_, 'u'), rest @ ..] => {
+                    *d = rest;
+                    *i
+                },
+                _ => return Err(ParseError::ErrorAt(start, "Expected low surrogate after high surrogate".to_string())),
+            };
+            match hex_digits(d, 4)? {
+                low @ 0xDC00..=0xDFFF => 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00),
+                _ => return Err(ParseError::ErrorAt(low_start, "Expected low surrogate after high surrogate".to_string())),
+            }
+        },
+        0xDC00..=0xDFFF => return Err(ParseError::ErrorAt(start, "Encountered low surrogate without high surrogate".to_string())),
+        code => code,
+    };
+    Ok(char::from_u32(code).expect("surrogates are excluded"))
+}
+
 impl<'a> Input<'a> {
 
     pub fn new(input : &'a [(usize, char)] ) -> Input<'a> { 
-        Input { data: input }
+        Input { data: input
+              , source: input
+              , memo: memo::MemoTable::new()
+              , depth: 0
+              , max_depth: DEFAULT_MAX_DEPTH
+              , fuel: None 
+              , whitespace: Whitespace::Default
+              }
+    }
+
+    /// Limits how deeply combinators may nest before failing with `DepthExceeded`.  The
+    /// default is `DEFAULT_MAX_DEPTH`.
+    pub fn with_max_depth(mut self, max_depth : usize) -> Input<'a> {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Limits how many parsers combinators may run before failing with `FuelExhausted`.
+    pub fn with_fuel(mut self, fuel : usize) -> Input<'a> {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Chooses what `clear`, and so every combinator that skips whitespace, skips.
+    pub fn with_whitespace(mut self, whitespace : Whitespace) -> Input<'a> {
+        self.whitespace = whitespace;
+        self
+    }
+
+    pub fn remaining_fuel(&self) -> Option<usize> {
+        self.fuel
     }
 
     pub fn expect_end(&mut self) -> Result<(), ParseError> {
@@ -44,6 +437,14 @@ impl<'a> Input<'a> {
             match d {
                 [] if comment > 0 => return Err(ParseError::EndOfFile("Expected end of comment but found end of file".to_string())),
                 [] => break,
+                _ if self.whitespace == Whitespace::Json => match d {
+                    [(_, ' ' | '\t' | '\n' | '\r'), rest @ ..] => d = rest,
+                    _ => break,
+                },
+                _ if self.whitespace == Whitespace::Blank => match d {
+                    [(_, ' ' | '\t'), rest @ ..] => d = rest,
+                    _ => break,
+                },
                 [(_, '/'), (_, '*'), rest @ ..] => {
                     comment += 1;
                     d = rest; 
@@ -53,6 +454,15 @@ impl<'a> Input<'a> {
                     d = rest; 
                 }, 
                 [_, rest @ ..] if comment > 0 => d = rest,
+                [(_, '/'), (_, '/'), rest @ ..] if self.whitespace == Whitespace::LineComments => {
+                    d = rest;
+                    while let [(_, x), rest @ ..] = d {
+                        if *x == '\n' {
+                            break;
+                        }
+                        d = rest;
+                    }
+                },
                 [(_, x), rest @ ..] if x.is_whitespace() => d = rest,
                 _ => break,
             }
@@ -61,6 +471,20 @@ impl<'a> Input<'a> {
         Ok(())
     }
 
+    /// Returns the next character after any whitespace and comments without consuming it.
+    pub fn peek_char(&mut self) -> Result<Option<char>, ParseError> {
+        let rp = self.create_restore();
+        self.clear()?;
+        let c = self.data.first().map(|(_, c)| *c);
+        self.restore(rp);
+        Ok(c)
+    }
+
+    /// Checks for end of input, ignoring whitespace and comments, without consuming anything.
+    pub fn at_end(&mut self) -> Result<bool, ParseError> {
+        Ok(self.peek_char()?.is_none())
+    }
+
     pub fn create_restore(&self) -> RestorePoint<'a> {
         RestorePoint{ data: self.data }
     }
@@ -69,6 +493,64 @@ impl<'a> Input<'a> {
         self.data = restore_point.data 
     }
 
+    fn unexpected(&self, expected : &str) -> ParseError {
+        match self.data {
+            [] => ParseError::EndOfFile(format!("Expected {} but found end of file", expected)),
+            [(i, x), ..] => ParseError::ErrorAt(*i, format!("Expected {} but found {}", expected, x)),
+        }
+    }
+
+    // byte index of the next character, or of the end of input
+    fn position(&self) -> usize {
+        match (self.data, self.source) {
+            ([(i, _), ..], _) => *i,
+            ([], [.., (i, c)]) => i + c.len_utf8(),
+            ([], []) => 0,
+        }
+    }
+
+    // whether the error happened further along than the current position
+    fn is_past(&self, error : &ParseError) -> bool {
+        match error {
+            ParseError::EndOfFile(_) => !self.data.is_empty(),
+            ParseError::ErrorAt(i, _) 
+            | ParseError::DepthExceeded(i) 
+            | ParseError::FuelExhausted(i) 
+            | ParseError::NoProgress(i) => self.data.first().is_some_and(|(j, _)| i > j),
+            ParseError::Fatal(e) => self.is_past(e),
+            ParseError::Context(_, e) => self.is_past(e),
+        }
+    }
+
+    fn offset(&self) -> usize {
+        self.source.len() - self.data.len()
+    }
+
+    // every combinator runs its parsers through here so that depth and fuel are checked
+    fn apply<T>(&mut self, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        self.enter()?;
+        let result = parse(self);
+        self.leave();
+        result
+    }
+
+    fn enter(&mut self) -> Result<(), ParseError> {
+        if self.depth >= self.max_depth {
+            return Err(ParseError::DepthExceeded(self.position()));
+        }
+        match self.fuel {
+            Some(0) => return Err(ParseError::FuelExhausted(self.position())),
+            Some(ref mut fuel) => *fuel -= 1,
+            None => (),
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
     fn raw_expect(&mut self,  s : &str) -> Result<(), ParseError>  {
         let mut d = self.data;
         for c in s.chars() {
@@ -88,6 +570,10 @@ impl<'a> Input<'a> {
     }
 
     pub fn parse_symbol(&mut self) -> Result<PSym, ParseError> {
+        self.parse_symbol_with(&SymbolSpec::new())
+    }
+
+    pub fn parse_symbol_with(&mut self, spec : &SymbolSpec) -> Result<PSym, ParseError> {
         self.clear()?;
 
         let mut d = self.data;
@@ -97,7 +583,7 @@ impl<'a> Input<'a> {
 
         match d {
             [] => return Err(ParseError::EndOfFile("parse_symbol".to_string())),
-            [(i, x), rest @ ..] if x.is_alphabetic() || *x == '_' => {
+            [(i, x), rest @ ..] if (spec.start)(*x) => {
                 d = rest;
                 cs.push(x);
                 start = *i;
@@ -109,7 +595,7 @@ impl<'a> Input<'a> {
         loop {
             match d {
                 [] => break,
-                [(i, x), rest @ ..] if x.is_alphanumeric() || *x == '_' => {
+                [(i, x), rest @ ..] if (spec.rest)(*x) => {
                     d = rest;
                     cs.push(x);
                     end = *i;
@@ -124,6 +610,10 @@ impl<'a> Input<'a> {
     }
 
     pub fn parse_number(&mut self) -> Result<PSym, ParseError> {
+        self.parse_number_with(NumberFormat::Default)
+    }
+
+    pub fn parse_number_with(&mut self, format : NumberFormat) -> Result<PSym, ParseError> {
         fn parse_digit(input : &mut Input) -> Result<char, ParseError> {
             input.choice( &[ |i| { i.raw_expect("0")?; Ok('0') }
                            , |i| { i.raw_expect("1")?; Ok('1') }
@@ -144,25 +634,37 @@ impl<'a> Input<'a> {
                            ])
         }
 
-        fn parse_scientific_notation(input : &mut Input, s : usize) -> PSym {
-            fn p(input : &mut Input) -> Result<Vec<char>, ParseError> {
+        fn parse_scientific_notation(input : &mut Input, s : usize, format : NumberFormat) -> PSym {
+            #[allow(clippy::redundant_pattern_matching)]
+            fn p(input : &mut Input, format : NumberFormat) -> Result<Vec<char>, ParseError> {
                 let mut e = vec![input.choice( &[ |i| { i.raw_expect("E")?; Ok('E') }
                                                 , |i| { i.raw_expect("e")?; Ok('e') }
                                                 ] )?];
-                let neg = input.maybe( |i| i.raw_expect("-") );
+                let neg = input.maybe( |i| i.raw_expect("-") )?;
+                let pos = match format {
+                    NumberFormat::Json if neg.is_none() => input.maybe( |i| i.raw_expect("+") )?,
+                    _ => None,
+                };
                 let mut digits = input.one_or_more(parse_digit)?;  
     
                 if matches!( neg, Some(_) ) {
                     e.push('-');
                 }
+                if pos.is_some() {
+                    e.push('+');
+                }
 
                 e.append(&mut digits);
 
                 Ok(e)
             }
 
-            match p(input) {
-                Err(_) => PSym { value: "".to_string(), start: s, end: s },
+            let rp = input.create_restore();
+            match p(input, format) {
+                Err(_) => {
+                    input.restore(rp);
+                    PSym { value: "".to_string(), start: s, end: s }
+                },
                 Ok(x) => vec_to_sym(x, s + 1),
             }
         }
@@ -184,15 +686,33 @@ impl<'a> Input<'a> {
         lead.append(&mut lead_rest);
         let lead = vec_to_sym(lead, start);
 
-        let maybe_decimal = self.maybe( |i| {
-            i.expect(".")?;
-            i.one_or_more(parse_digit)
-        } );
+        if format == NumberFormat::Json {
+            let digits = lead.value.trim_start_matches('-');
+            if digits.is_empty() {
+                return Err(error_at(self.data, "Expected digit in parse_number".to_string()));
+            }
+            if digits.len() > 1 && digits.starts_with('0') {
+                let i = lead.end + 1 - digits.len() + 1;
+                return Err(ParseError::ErrorAt(i, "Encountered leading zero in parse_number".to_string()));
+            }
+        }
+
+        // JSON allows no whitespace before the `.`
+        let maybe_decimal = match format {
+            NumberFormat::Default => self.maybe( |i| {
+                i.expect(".")?;
+                i.one_or_more(parse_digit)
+            } )?,
+            NumberFormat::Json => self.maybe( |i| {
+                i.raw_expect(".")?;
+                i.one_or_more(parse_digit)
+            } )?,
+        };
         
         match maybe_decimal {
             Some(decimal) => {
                 let decimal = vec_to_sym(decimal, lead.end + 1);
-                let sn = parse_scientific_notation(self, decimal.end + 1); 
+                let sn = parse_scientific_notation(self, decimal.end + 1, format); 
 
                 Ok(PSym { value: format!( "{}.{}{}"
                                         , lead.value
@@ -204,7 +724,7 @@ impl<'a> Input<'a> {
                         })
             }
             None => {
-                let sn = parse_scientific_notation(self, lead.end); 
+                let sn = parse_scientific_notation(self, lead.end, format); 
 
                 Ok(PSym { value: format!( "{}{}"
                                         , lead.value
@@ -218,62 +738,71 @@ impl<'a> Input<'a> {
     }
 
     pub fn parse_string(&mut self) -> Result<PSym, ParseError> {
+        self.parse_string_with(&StringSpec::new())
+    }
+
+    pub fn parse_string_with(&mut self, spec : &StringSpec) -> Result<PSym, ParseError> {
         self.clear()?;
 
         let mut d = self.data;
         let mut cs = vec![];
         let start : usize;
         let end : usize;
+        let quotes = if spec.multiline { 3 } else { 1 };
 
         match d {
             [] => return Err(ParseError::EndOfFile("parse_string".to_string())),
-            [(i, '"'), rest @ ..] => {
-                d = rest;
+            [(i, _), ..] if d.iter().take(quotes).filter(|(_, x)| *x == spec.quote).count() == quotes => {
+                d = &d[quotes ..];
                 start = *i;
             },
             [(i, x), ..] => return Err(ParseError::ErrorAt(*i, format!("Encountered {} at the beginning of parse_string", x))),
         }
 
-        let mut escape = false;
+        if spec.multiline {
+            if let [(_, '\n'), rest @ ..] | [(_, '\r'), (_, '\n'), rest @ ..] = d {
+                d = rest;
+            }
+        }
+
         loop {
             match d {
                 [] => return Err(ParseError::EndOfFile("parse_string".to_string())),
-                [(_, '\\'), rest @ ..] if escape => {
-                    escape = false;
+                [(i, q), rest @ ..] if *q == spec.quote && !spec.multiline => {
                     d = rest;
-                    cs.push('\\');
-                },
-                [(_, 'n'), rest @ ..] if escape => {
-                    escape = false;
-                    d = rest;
-                    cs.push('\n');
-                },
-                [(_, 'r'), rest @ ..] if escape => {
-                    escape = false;
-                    d = rest;
-                    cs.push('\r');
+                    end = *i;
+                    break;
                 },
-                [(_, '0'), rest @ ..] if escape => {
-                    escape = false;
-                    d = rest;
-                    cs.push('\0');
+                [(_, q), ..] if *q == spec.quote => {
+                    let run = d.iter().take_while(|(_, x)| *x == spec.quote).count();
+                    if run > 5 {
+                        return Err(ParseError::ErrorAt(d[5].0, format!("Encountered too many {} at the ending of parse_string", q)));
+                    }
+                    if run < 3 {
+                        cs.extend(std::iter::repeat_n(spec.quote, run));
+                        d = &d[run ..];
+                        continue;
+                    }
+                    // up to two quotes may come right before the closing ones
+                    cs.extend(std::iter::repeat_n(spec.quote, run - 3));
+                    end = d[run - 1].0;
+                    d = &d[run ..];
+                    break;
                 },
-                [(_, 't'), rest @ ..] if escape => {
-                    escape = false;
+                [(_, '\r'), (_, '\n'), rest @ ..] | [(_, '\n'), rest @ ..] if spec.multiline => {
                     d = rest;
-                    cs.push('\t');
+                    cs.push('\n');
                 },
-                [(_, '"'), rest @ ..] if escape => {
-                    escape = false;
-                    d = rest;
-                    cs.push('"');
+                [(_, '\\'), rest @ ..] if spec.multiline && spec.format == StringFormat::Toml && ends_line(rest) => {
+                    let skip = rest.iter().take_while(|(_, x)| x.is_ascii_whitespace()).count();
+                    d = &rest[skip ..];
                 },
-                [(i, x), ..] if escape => return Err(ParseError::ErrorAt(*i, format!("Encountered unknown escape character {}", x))),
-                [(_, '\\'), rest @ ..] => {
-                    escape = true;
+                [(i, '\\'), rest @ ..] if spec.format != StringFormat::TomlLiteral => {
                     d = rest;
+                    cs.push(string_escape(&mut d, *i, spec)?);
                 },
-                [(_, '"'), ..] => break,
+                [(i, x), ..] if spec.format.forbids(*x) =>
+                    return Err(ParseError::ErrorAt(*i, format!("Encountered control character {:?} in parse_string", x))),
                 [(_, x), rest @ ..] => {
                     d = rest;
                     cs.push(*x);
@@ -281,38 +810,76 @@ impl<'a> Input<'a> {
             }
         }
 
-        match d {
-            [] => return Err(ParseError::EndOfFile("parse_string".to_string())),
-            [(i, '"'), rest @ ..] => {
-                d = rest;
-                end = *i;
-            },
-            [(i, x), ..] => return Err(ParseError::ErrorAt(*i, format!("Encountered {} at the ending of parse_string", x))),
-        }
-
         self.data = d;
 
         Ok( PSym { start, end, value: cs.into_iter().collect::<String>() } )
     }
 
-    pub fn maybe<T>(&mut self, parse : fn(&mut Input) -> Result<T, ParseError>) -> Option<T> {
+    /// Runs `parse` and turns any error into a fatal error so that enclosing combinators
+    /// stop backtracking.  Use it once an alternative has matched enough to be sure it is 
+    /// the right one.  The error passes through every enclosing `choice`, not just the 
+    /// innermost, so a cut commits to all of the alternatives that led to it.
+    pub fn cut<T>(&mut self, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        match self.apply(parse) {
+            Err(e) if !e.is_fatal() => Err(ParseError::Fatal(Box::new(e))),
+            result => result,
+        }
+    }
+
+    /// Labels any error from `parse` with a human readable context such as 
+    /// "while parsing parameter list".
+    pub fn context<T>(&mut self, label : &str, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        self.apply(parse).map_err(|e| ParseError::Context(label.to_string(), Box::new(e)))
+    }
+
+    /// Gives `None` when `parse` fails, except that fatal errors are returned instead of
+    /// backtracked over.
+    pub fn maybe<T>(&mut self, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<Option<T>, ParseError> {
         let rp = self.create_restore(); 
-        match parse(self) {
-            Ok(v) => Some(v),
+        match self.apply(parse) {
+            Ok(v) => Ok(Some(v)),
+            Err(e) if e.is_fatal() => Err(e),
             Err(_) => { 
                 self.restore(rp);
-                None 
+                Ok(None)
             },
         }
     }
 
+    /// Succeeds when `parse` succeeds, but never consumes any input.
+    pub fn peek<T>(&mut self, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        let rp = self.create_restore();
+        let result = self.apply(parse);
+        self.restore(rp);
+        result
+    }
+
+    /// Succeeds only when `parse` fails, and never consumes any input.
+    pub fn not_followed_by<T>(&mut self, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<(), ParseError> {
+        let rp = self.create_restore();
+        let result = self.apply(parse);
+        self.restore(rp);
+        match result {
+            Ok(_) => {
+                self.clear()?;
+                let e = error_at(self.data, "Encountered input rejected by not_followed_by".to_string());
+                self.restore(rp);
+                Err(e)
+            },
+            Err(e) if e.is_fatal() => Err(e),
+            Err(_) => Ok(()),
+        }
+    }
+
     pub fn zero_or_more<T>(&mut self, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
         let mut items = vec![];
 
         loop {
             let rp = self.create_restore(); 
-            match parse(self) {
+            match self.apply(parse) {
+                Ok(_) if self.data.len() == rp.data.len() => return Err(ParseError::NoProgress(self.position())),
                 Ok(v) => items.push(v),
+                Err(e) if e.is_fatal() => return Err(e),
                 Err(_) => {
                     self.restore(rp);
                     break
@@ -326,12 +893,14 @@ impl<'a> Input<'a> {
     pub fn one_or_more<T>(&mut self, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
         let mut items = vec![];
 
-        items.push( parse(self)? );
+        items.push( self.apply(parse)? );
 
         loop {
             let rp = self.create_restore(); 
-            match parse(self) {
+            match self.apply(parse) {
+                Ok(_) if self.data.len() == rp.data.len() => return Err(ParseError::NoProgress(self.position())),
                 Ok(v) => items.push(v),
+                Err(e) if e.is_fatal() => return Err(e),
                 Err(_) => {
                     self.restore(rp); 
                     break
@@ -342,13 +911,80 @@ impl<'a> Input<'a> {
         Ok(items)
     }
 
+    /// Parses items until `end` is found.  The terminator is consumed.
+    pub fn many_till<T>(&mut self, parse : fn(&mut Input) -> Result<T, ParseError>, end : &str) -> Result<Vec<T>, ParseError> {
+        let mut items = vec![];
+
+        loop {
+            let rp = self.create_restore();
+            if self.expect(end).is_ok() {
+                return Ok(items);
+            }
+            self.restore(rp);
+
+            match self.apply(parse) {
+                Ok(_) if self.data.len() == rp.data.len() => return Err(ParseError::NoProgress(self.position())),
+                Ok(v) => items.push(v),
+                Err(e) if e.is_fatal() => return Err(e),
+                Err(e) => {
+                    self.restore(rp);
+                    self.clear()?;
+                    if self.is_past(&e) {
+                        return Err(e);
+                    }
+                    return Err(self.unexpected(&format!("`{}`", end)));
+                },
+            }
+        }
+    }
+
+    pub fn count<T>(&mut self, n : usize, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
+        let mut items = Vec::with_capacity(n);
+        for _ in 0..n {
+            items.push(self.apply(parse)?);
+        }
+        Ok(items)
+    }
+
+    pub fn between<T>(&mut self, open : &str, parse : fn(&mut Input) -> Result<T, ParseError>, close : &str) -> Result<T, ParseError> {
+        self.expect(open)?;
+        let item = self.apply(parse)?;
+
+        let rp = self.create_restore();
+        if self.expect(close).is_err() {
+            self.restore(rp);
+            self.clear()?;
+            return Err(self.unexpected(&format!("`{}`", close)));
+        }
+
+        Ok(item)
+    }
+
+    /// Skips input until `sync` is next, leaving `sync` unconsumed.  Useful for recovering
+    /// after an error.
+    pub fn skip_until(&mut self, sync : &str) -> Result<(), ParseError> {
+        loop {
+            let rp = self.create_restore();
+            if self.raw_expect(sync).is_ok() {
+                self.restore(rp);
+                return Ok(());
+            }
+            match self.data {
+                [] => return Err(ParseError::EndOfFile(format!("Expected `{}` but found end of file", sync))),
+                [_, rest @ ..] => self.data = rest,
+            }
+        }
+    }
+
+    #[allow(clippy::while_let_loop)]
     pub fn list<T>(&mut self, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
         let mut items = vec![];
 
         // check to see if this is an empty list
         let rp = self.create_restore();
-        match parse(self) {
+        match self.apply(parse) {
             Ok(item) => items.push(item),
+            Err(e) if e.is_fatal() => return Err(e),
             Err(_) => {
                 self.restore(rp); 
                 return Ok(vec![]);
@@ -360,21 +996,107 @@ impl<'a> Input<'a> {
                 Ok(_) => (),
                 Err(_) => break,
             }
-            items.push(parse(self)?);
+            items.push(self.apply(parse)?);
+        }
+
+        Ok(items)
+    }
+
+    pub fn sep_by<T>(&mut self, sep : &str, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
+        self.separated(&ListSpec::new(sep), parse)
+    }
+
+    pub fn sep_by1<T>(&mut self, sep : &str, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
+        self.separated(&ListSpec::new(sep).min(1), parse)
+    }
+
+    pub fn delimited_list<T>(&mut self, open : &str, sep : &str, close : &str, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
+        self.separated(&ListSpec::new(sep).delimited(open, close), parse)
+    }
+
+    pub fn separated<T>(&mut self, spec : &ListSpec, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
+        self.clear()?;
+        let start = self.data;
+
+        if let Some((open, _)) = spec.delimiters {
+            self.expect(open)?;
+        }
+
+        let mut items = vec![];
+        let mut missing_sep = false;
+        let mut item_error = None;
+        loop {
+            let rp = self.create_restore();
+            match self.apply(parse) {
+                Ok(item) => items.push(item),
+                Err(e) if e.is_fatal() => return Err(e),
+                Err(e) if items.is_empty() || spec.trailing != TrailingSeparator::Forbid => {
+                    self.restore(rp);
+                    item_error = Some(e);
+                    break;
+                },
+                Err(e) => return Err(e),
+            }
+
+            let at_max = spec.max.is_some_and(|max| items.len() >= max);
+            if at_max && spec.trailing == TrailingSeparator::Forbid {
+                break;
+            }
+
+            let rp = self.create_restore();
+            match self.expect(spec.sep) {
+                Ok(()) if at_max => break,
+                Ok(()) => (),
+                Err(_) if spec.trailing == TrailingSeparator::Require => {
+                    self.restore(rp);
+                    self.clear()?;
+                    return Err(self.unexpected(&format!("`{}`", spec.sep)));
+                },
+                Err(_) => {
+                    self.restore(rp);
+                    missing_sep = !at_max;
+                    break;
+                },
+            }
+        }
+
+        if let Some((_, close)) = spec.delimiters {
+            let rp = self.create_restore();
+            if self.expect(close).is_err() {
+                self.restore(rp);
+                self.clear()?;
+                // an item that failed part way explains more than the missing close
+                if let Some(e) = item_error.filter(|e| self.is_past(e)) {
+                    return Err(e);
+                }
+                if missing_sep {
+                    return Err(self.unexpected(&format!("`{}` or `{}`", spec.sep, close)));
+                }
+                return Err(self.unexpected(&format!("`{}`", close)));
+            }
+        }
+
+        if items.len() < spec.min {
+            if let Some(e) = item_error.filter(|e| self.is_past(e)) {
+                return Err(e);
+            }
+            return Err(error_at(start, format!("Expected at least {} items but found {}", spec.min, items.len())));
         }
 
         Ok(items)
     }
 
+    #[allow(clippy::type_complexity, clippy::len_zero)]
     pub fn choice<T>(&mut self, parsers : &[fn(&mut Input) -> Result<T, ParseError>]) -> Result<T, ParseError> {
 
         assert!( parsers.len() > 0, "choice must have at least one parser" );
 
         let mut e = None;
-        for parse in parsers.iter() {
+        for parse in parsers.iter().copied() {
             let rp = self.create_restore();
-            match parse(self) {
+            match self.apply(parse) {
                 Ok(item) => return Ok(item),
+                Err(err) if err.is_fatal() => return Err(err),
                 Err(err) => {
                     e = Some(err);
                     self.restore(rp);
@@ -387,12 +1109,14 @@ impl<'a> Input<'a> {
 }
 
 #[cfg(test)]
+#[allow(clippy::into_iter_on_ref)]
 mod test {
     use super::*;
 
     #[test]
     fn should_parse_single_character_symbol_second() -> Result<(), ParseError> {
-        let mut input = Input { data: &"::<>:: b d".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "::<>:: b d".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         input.expect("::<>::")?;
         let sym = input.parse_symbol()?;
         assert_eq!( sym.value, "b" );
@@ -404,7 +1128,8 @@ mod test {
 
     #[test]
     fn should_parse_double_character_symbol_second() -> Result<(), ParseError> {
-        let mut input = Input { data: &"::<>:: bb d".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "::<>:: bb d".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         input.expect("::<>::")?;
         let sym = input.parse_symbol()?;
         assert_eq!( sym.value, "bb" );
@@ -416,7 +1141,8 @@ mod test {
 
     #[test]
     fn should_parse_single_digit_number_second() -> Result<(), ParseError> {
-        let mut input = Input { data: &"::<>:: 1 d".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "::<>:: 1 d".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         input.expect("::<>::")?;
         let sym = input.parse_number()?;
         assert_eq!( sym.value, "1" );
@@ -428,7 +1154,8 @@ mod test {
 
     #[test]
     fn should_parse_double_digit_number_second() -> Result<(), ParseError> {
-        let mut input = Input { data: &"::<>:: 11 d".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "::<>:: 11 d".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         input.expect("::<>::")?;
         let sym = input.parse_number()?;
         assert_eq!( sym.value, "11" );
@@ -440,7 +1167,8 @@ mod test {
 
     #[test]
     fn should_expect_string() -> Result<(), ParseError> {
-        let mut input = Input { data: &"::<>::".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "::<>::".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         input.expect("::<>::")?;
         assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), "".to_string() ); 
         Ok(())
@@ -448,7 +1176,8 @@ mod test {
 
     #[test]
     fn should_parse_symbol() -> Result<(), ParseError> {
-        let mut input = Input { data: &"_Symbol_123".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "_Symbol_123".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let PSym { start, end, value: symbol } = input.parse_symbol()?;
         assert_eq!( start, 0 );
         assert_eq!( end, 10 );
@@ -459,7 +1188,8 @@ mod test {
 
     #[test]
     fn should_clear_whitespace() -> Result<(), ParseError> {
-        let mut input = Input { data: &"   x".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "   x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         input.clear()?;
         assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), "x".to_string() ); 
         Ok(())
@@ -467,13 +1197,14 @@ mod test {
 
     #[test]
     fn should_clear_block_comment() -> Result<(), ParseError> {
-        let mut input = Input { data: &r#"  
+        let data = r#"  
         
         /* comments %^& 124
 
         */
         
-        x"#.char_indices().collect::<Vec<(usize, char)>>() };
+        x"#.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         input.clear()?;
         assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), "x".to_string() ); 
         Ok(())
@@ -481,7 +1212,7 @@ mod test {
 
     #[test]
     fn should_clear_nested_block_comment() -> Result<(), ParseError> {
-        let mut input = Input { data: &r#"  
+        let data = r#"  
         
         /* comments %^& 124
 
@@ -491,7 +1222,8 @@ mod test {
 
         */
         
-        x"#.char_indices().collect::<Vec<(usize, char)>>() };
+        x"#.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         input.clear()?;
         assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), "x".to_string() ); 
         Ok(())
@@ -499,7 +1231,8 @@ mod test {
 
     #[test]
     fn should_parse_int_followed_by_dot() -> Result<(), ParseError> {
-        let mut input = Input { data: &"1234.".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "1234.".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let PSym { start, end, value: number } = input.parse_number()?;
         input.expect(".")?;
         assert_eq!( start, 0 );
@@ -511,7 +1244,8 @@ mod test {
 
     #[test]
     fn should_parse_int() -> Result<(), ParseError> {
-        let mut input = Input { data: &"1234".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "1234".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let PSym { start, end, value: number } = input.parse_number()?;
         assert_eq!( start, 0 );
         assert_eq!( end, 3 );
@@ -522,7 +1256,8 @@ mod test {
 
     #[test]
     fn should_parse_float() -> Result<(), ParseError> {
-        let mut input = Input { data: &"12.34".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "12.34".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let PSym { start, end, value: number } = input.parse_number()?;
         assert_eq!( start, 0 );
         assert_eq!( end, 4 );
@@ -533,7 +1268,8 @@ mod test {
 
     #[test]
     fn should_parse_scientific_notation() -> Result<(), ParseError> {
-        let mut input = Input { data: &"1234e42".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "1234e42".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let PSym { start, end, value: number } = input.parse_number()?;
         assert_eq!( start, 0 );
         assert_eq!( end, 6 );
@@ -544,7 +1280,8 @@ mod test {
 
     #[test]
     fn should_parse_scientific_notation_on_float() -> Result<(), ParseError> {
-        let mut input = Input { data: &"1234.4321e42".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "1234.4321e42".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let PSym { start, end, value: number } = input.parse_number()?;
         assert_eq!( start, 0 );
         assert_eq!( end, 11 );
@@ -555,7 +1292,8 @@ mod test {
 
     #[test]
     fn should_parse_negative_scientific_notation() -> Result<(), ParseError> {
-        let mut input = Input { data: &"1234E-42".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "1234E-42".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let PSym { start, end, value: number } = input.parse_number()?;
         assert_eq!( start, 0 );
         println!("number {}", number);
@@ -567,7 +1305,8 @@ mod test {
 
     #[test]
     fn should_parse_negative_int() -> Result<(), ParseError> {
-        let mut input = Input { data: &"-1234".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "-1234".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let PSym { start, end, value: number } = input.parse_number()?;
         assert_eq!( start, 0 );
         assert_eq!( end, 4 );
@@ -578,8 +1317,9 @@ mod test {
 
     #[test]
     fn should_parse_string_with_whitespace() -> Result<(), ParseError> {
-        let mut input = Input { data: &r#" /* */ " string with 123
-whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
+        let data = r#" /* */ " string with 123
+whitespace ""#.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let PSym { start, end, value: number } = input.parse_string()?;
         assert_eq!( start, 7 );
         assert_eq!( end, 36 );
@@ -590,7 +1330,8 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
 
     #[test]
     fn should_parse_string_with_escapes() -> Result<(), ParseError> {
-        let mut input = Input { data: &r#" /* */ "\\ \0 \n \r \t \"""#.char_indices().collect::<Vec<(usize, char)>>() };
+        let data = r#" /* */ "\\ \0 \n \r \t \"""#.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let PSym { start, end, value: number } = input.parse_string()?;
         assert_eq!( start, 7 );
         assert_eq!( end, 25 );
@@ -599,9 +1340,90 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
         Ok(())
     }
 
+    #[test]
+    fn should_parse_string_with_json_escapes() -> Result<(), ParseError> {
+        let data = r#"'\' \/ \b \f é 😀'"#.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let s = input.parse_string_with(&StringSpec::new().quote('\'').format(StringFormat::Json))?;
+        assert_eq!( s.value, "' / \u{8} \u{c} é 😀" );
+        assert_eq!( (s.start, s.end), (0, 20) );
+
+        let parse = |s : &str| {
+            let data = s.char_indices().collect::<Vec<(usize, char)>>();
+            let mut input = Input::new(&data);
+            input.parse_string_with(&StringSpec::new().format(StringFormat::Json)).map(|s| s.value)
+        };
+        assert!( matches!( parse(r#""\0""#), Err(ParseError::ErrorAt(2, _)) ) );
+        assert!( matches!( parse("\"a\tb\""), Err(ParseError::ErrorAt(2, _)) ) );
+        assert!( matches!( parse(r#""x\uD800\n""#), Err(ParseError::ErrorAt(2, _)) ) );
+        assert!( matches!( parse(r#""\uDC00""#), Err(ParseError::ErrorAt(1, _)) ) );
+        assert!( matches!( parse(r#""\u12""#), Err(ParseError::ErrorAt(5, _)) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_multiline_string() -> Result<(), ParseError> {
+        let data = "\"\"\"\nline \\\n    next \\u00e9\r\nquote \"\"\"\"\" rest".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let s = input.parse_string_with(&StringSpec::new().format(StringFormat::Toml).multiline())?;
+        assert_eq!( s.value, "line next é\nquote \"\"" );
+        assert_eq!( (s.start, s.end), (0, 38) );
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), " rest".to_string() ); 
+
+        let data = r"'''a\n''''".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let s = input.parse_string_with(&StringSpec::new().quote('\'').format(StringFormat::TomlLiteral).multiline())?;
+        assert_eq!( s.value, "a\\n'" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_symbol_with_spec() -> Result<(), ParseError> {
+        let data = "1st-key rest".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let spec = SymbolSpec::new().start(|c| c.is_ascii_alphanumeric()).rest(|c| c.is_ascii_alphanumeric() || c == '-');
+        let PSym { start, end, value } = input.parse_symbol_with(&spec)?;
+        assert_eq!( (start, end, value.as_str()), (0, 6, "1st-key") );
+        assert!( input.parse_symbol_with(&spec.start(|c| c == '_')).is_err() );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_number_with_json_format() -> Result<(), ParseError> {
+        let parse = |s : &str, format| {
+            let data = s.char_indices().collect::<Vec<(usize, char)>>();
+            let mut input = Input::new(&data);
+            let n = input.parse_number_with(format)?;
+            Ok((n.value, input.data.into_iter().map(|(_,x)| x).collect::<String>()))
+        };
+        assert_eq!( parse("-0.5E+3,", NumberFormat::Json)?, ("-0.5E+3".to_string(), ",".to_string()) );
+        assert_eq!( parse("1e+3", NumberFormat::Default)?, ("1".to_string(), "e+3".to_string()) );
+        assert_eq!( parse("1 .5", NumberFormat::Json)?, ("1".to_string(), " .5".to_string()) );
+        assert_eq!( parse("012", NumberFormat::Default)?.0, "012" );
+        assert!( matches!( parse("012", NumberFormat::Json), Err(ParseError::ErrorAt(1, _)) ) );
+        assert!( matches!( parse("-", NumberFormat::Json), Err(ParseError::EndOfFile(_)) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_clear_chosen_whitespace() -> Result<(), ParseError> {
+        let data = " // line\n /* block */ x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data).with_whitespace(Whitespace::LineComments);
+        input.clear()?;
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), "x".to_string() ); 
+
+        let data = " \t\r\n\u{c}x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data).with_whitespace(Whitespace::Json);
+        input.clear()?;
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), "\u{c}x".to_string() ); 
+        Ok(())
+    }
+
+    #[allow(clippy::bool_assert_comparison, clippy::redundant_pattern_matching)]
     #[test]
     fn should_restore() -> Result<(), ParseError> {
-        let mut input = Input { data: &"-1234".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "-1234".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let r = input.create_restore();
         let PSym { start, end, value: number } = input.parse_number()?;
         assert_eq!( start, 0 );
@@ -623,7 +1445,8 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
 
     #[test]
     fn should_handle_multiple_restores() -> Result<(), ParseError> {
-        let mut input = Input { data: &"-1234 789".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "-1234 789".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let r1 = input.create_restore();
 
         let PSym { start, end, value: number } = input.parse_number()?;
@@ -667,8 +1490,9 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
 
     #[test]
     fn should_parse_maybe_parser() -> Result<(), ParseError> {
-        let mut input = Input { data: &"-1234".char_indices().collect::<Vec<(usize, char)>>() };
-        let PSym { start, end, value: number } = input.maybe(|i| i.parse_number()).unwrap();
+        let data = "-1234".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let PSym { start, end, value: number } = input.maybe(|i| i.parse_number())?.unwrap();
         assert_eq!( start, 0 );
         assert_eq!( end, 4 );
         assert_eq!( number, "-1234" );
@@ -678,8 +1502,9 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
 
     #[test]
     fn should_parse_maybe_parser_with_nothing() -> Result<(), ParseError> {
-        let mut input = Input { data: &"x".char_indices().collect::<Vec<(usize, char)>>() };
-        let number = input.maybe(|i| i.parse_number());
+        let data = "x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let number = input.maybe(|i| i.parse_number())?;
         match number {
             None => (),
             _ => panic!( "nothing should be parsed" ), 
@@ -690,7 +1515,8 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
 
     #[test]
     fn should_parse_zero_or_more_with_some_items() -> Result<(), ParseError> {
-        let mut input = Input { data: &"123 456".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "123 456".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let numbers = input.zero_or_more(|i| i.parse_number())?;
         assert_eq!( numbers.len(), 2 );
         assert_eq!( numbers[0].value, "123" );
@@ -701,7 +1527,8 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
 
     #[test]
     fn should_parse_zero_or_more_with_no_items() -> Result<(), ParseError> {
-        let mut input = Input { data: &"x".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let numbers = input.zero_or_more(|i| i.parse_number())?;
         assert_eq!( numbers.len(), 0 );
         assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), "x".to_string() ); 
@@ -710,7 +1537,8 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
 
     #[test]
     fn should_parse_one_or_more_with_some_items() -> Result<(), ParseError> {
-        let mut input = Input { data: &"123 456".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "123 456".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let numbers = input.one_or_more(|i| i.parse_number())?;
         assert_eq!( numbers.len(), 2 );
         assert_eq!( numbers[0].value, "123" );
@@ -721,7 +1549,8 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
 
     #[test]
     fn should_parse_one_or_more_with_one_item() -> Result<(), ParseError> {
-        let mut input = Input { data: &"123".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "123".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let numbers = input.one_or_more(|i| i.parse_number())?;
         assert_eq!( numbers.len(), 1 );
         assert_eq!( numbers[0].value, "123" );
@@ -729,9 +1558,11 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
         Ok(())
     }
 
+    #[allow(clippy::single_match)]
     #[test]
     fn should_fail_one_or_more_with_no_item() -> Result<(), ParseError> {
-        let mut input = Input { data: &"x".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let numbers = input.one_or_more(|i| i.parse_number());
         match numbers {
             Ok(_) => panic!( "one or more should fail on no items" ),
@@ -743,7 +1574,8 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
 
     #[test]
     fn should_parse_empty_list() -> Result<(), ParseError> {
-        let mut input = Input { data: &"x".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let items = input.list(|i| i.parse_number())?;
         assert_eq!( items.len(), 0 );
         assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), "x".to_string() ); 
@@ -752,7 +1584,8 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
 
     #[test]
     fn should_parse_single_item_list() -> Result<(), ParseError> {
-        let mut input = Input { data: &"123".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "123".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let items = input.list(|i| i.parse_number())?;
         assert_eq!( items.len(), 1 );
         assert_eq!( items[0].value, "123" );
@@ -762,7 +1595,8 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
 
     #[test]
     fn should_parse_list() -> Result<(), ParseError> {
-        let mut input = Input { data: &"123, 456, 789".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "123, 456, 789".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let items = input.list(|i| i.parse_number())?;
         assert_eq!( items.len(), 3 );
         assert_eq!( items[0].value, "123" );
@@ -774,7 +1608,8 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
 
     #[test]
     fn should_parse_first_choice() -> Result<(), ParseError> {
-        let mut input = Input { data: &"123".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "123".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let item = input.choice(&[ |i : &mut Input| -> Result<PSym, ParseError> { i.parse_number() }
                                 , |i : &mut Input| -> Result<PSym, ParseError> { i.parse_symbol() }
                                 ])?;
@@ -785,7 +1620,8 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
 
     #[test]
     fn should_parse_second_choice() -> Result<(), ParseError> {
-        let mut input = Input { data: &"blah".char_indices().collect::<Vec<(usize, char)>>() };
+        let data = "blah".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
         let item = input.choice(&[ |i : &mut Input| -> Result<PSym, ParseError> { i.parse_number() }
                                 , |i : &mut Input| -> Result<PSym, ParseError> { i.parse_symbol() }
                                 ])?;
@@ -793,4 +1629,351 @@ whitespace ""#.char_indices().collect::<Vec<(usize, char)>>() };
         assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), "".to_string() ); 
         Ok(())
     }
+
+    #[test]
+    fn should_parse_sep_by() -> Result<(), ParseError> {
+        let data = "1; 2 ;3 x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let items = input.sep_by(";", |i| i.parse_number())?;
+        assert_eq!( items.len(), 3 );
+        assert_eq!( items[2].value, "3" );
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), " x".to_string() ); 
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_sep_by1_with_no_items() -> Result<(), ParseError> {
+        let data = "  x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let items = input.sep_by1(",", |i| i.parse_number());
+        assert!( matches!( items, Err(ParseError::ErrorAt(2, _)) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_delimited_list() -> Result<(), ParseError> {
+        let data = "[1, 2, 3] x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let items = input.delimited_list("[", ",", "]", |i| i.parse_number())?;
+        assert_eq!( items.len(), 3 );
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), " x".to_string() ); 
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_empty_delimited_list() -> Result<(), ParseError> {
+        let data = "[ ]".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let items = input.delimited_list("[", ",", "]", |i| i.parse_number())?;
+        assert_eq!( items.len(), 0 );
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), "".to_string() ); 
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_missing_separator_or_close() -> Result<(), ParseError> {
+        let data = "[1, 2 3]".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let items = input.delimited_list("[", ",", "]", |i| i.parse_number());
+        match items {
+            Err(ParseError::ErrorAt(6, message)) => assert_eq!( message, "Expected `,` or `]` but found 3" ),
+            _ => panic!( "expected error at 6" ),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_handle_trailing_separator_policies() -> Result<(), ParseError> {
+        let data = "(1, 2,)".char_indices().collect::<Vec<(usize, char)>>();
+
+        let mut input = Input::new(&data);
+        let items = input.delimited_list("(", ",", ")", |i| i.parse_number());
+        assert!( items.is_err() );
+
+        let mut input = Input::new(&data);
+        let spec = ListSpec::new(",").delimited("(", ")").trailing(TrailingSeparator::Allow);
+        let items = input.separated(&spec, |i| i.parse_number())?;
+        assert_eq!( items.len(), 2 );
+
+        let mut input = Input::new(&data);
+        let spec = ListSpec::new(",").delimited("(", ")").trailing(TrailingSeparator::Require);
+        let items = input.separated(&spec, |i| i.parse_number())?;
+        assert_eq!( items.len(), 2 );
+
+        let data = "(1, 2)".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let items = input.separated(&spec, |i| i.parse_number());
+        match items {
+            Err(ParseError::ErrorAt(5, message)) => assert_eq!( message, "Expected `,` but found )" ),
+            _ => panic!( "expected error at 5" ),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_respect_list_counts() -> Result<(), ParseError> {
+        let data = "1, 2, 3".char_indices().collect::<Vec<(usize, char)>>();
+
+        let mut input = Input::new(&data);
+        let items = input.separated(&ListSpec::new(",").max(2), |i| i.parse_number())?;
+        assert_eq!( items.len(), 2 );
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), ", 3".to_string() ); 
+
+        let mut input = Input::new(&data);
+        let items = input.separated(&ListSpec::new(",").min(4), |i| i.parse_number());
+        assert!( matches!( items, Err(ParseError::ErrorAt(0, _)) ) );
+
+        let data = "[1, 2, 3]".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let items = input.separated(&ListSpec::new(",").delimited("[", "]").max(2), |i| i.parse_number());
+        match items {
+            Err(ParseError::ErrorAt(5, message)) => assert_eq!( message, "Expected `]` but found ," ),
+            _ => panic!( "expected error at 5" ),
+        }
+        Ok(())
+    }
+
+    fn if_statement(input : &mut Input) -> Result<PSym, ParseError> {
+        input.expect("if")?;
+        input.cut(|i| {
+            i.expect("(")?;
+            let cond = i.parse_symbol()?;
+            i.expect(")")?;
+            Ok(cond)
+        })
+    }
+
+    #[test]
+    fn should_propagate_fatal_error_through_choice() -> Result<(), ParseError> {
+        let data = "if (x y".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let item = input.choice(&[ if_statement
+                                 , |i : &mut Input| -> Result<PSym, ParseError> { i.parse_symbol() }
+                                 ]);
+        match item {
+            Err(ParseError::Fatal(e)) => assert!( matches!( *e, ParseError::ErrorAt(6, _) ) ),
+            _ => panic!( "expected fatal error" ),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_backtrack_before_cut() -> Result<(), ParseError> {
+        let data = "iffy".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let item = input.choice(&[ |i : &mut Input| -> Result<PSym, ParseError> { i.expect("(")?; if_statement(i) }
+                                 , |i : &mut Input| -> Result<PSym, ParseError> { i.parse_symbol() }
+                                 ])?;
+        assert_eq!( item.value, "iffy" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_propagate_fatal_error_through_maybe() {
+        let data = "if (x y".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let item = input.choice(&[ |i : &mut Input| -> Result<Option<PSym>, ParseError> { i.maybe(if_statement) }
+                                 , |i : &mut Input| -> Result<Option<PSym>, ParseError> { Ok(Some(i.parse_symbol()?)) }
+                                 ]);
+        match item {
+            Err(ParseError::Fatal(e)) => assert!( matches!( *e, ParseError::ErrorAt(6, _) ) ),
+            _ => panic!( "expected fatal error" ),
+        }
+    }
+
+    #[test]
+    fn should_propagate_fatal_error_through_repetition() -> Result<(), ParseError> {
+        let data = "if (a) if (b) if c".char_indices().collect::<Vec<(usize, char)>>();
+
+        let mut input = Input::new(&data);
+        assert!( input.zero_or_more(if_statement).map_err(|e| e.is_fatal()).unwrap_err() );
+
+        let mut input = Input::new(&data);
+        assert!( input.maybe(|i| { if_statement(i)?; if_statement(i)?; if_statement(i) }).map_err(|e| e.is_fatal()).unwrap_err() );
+
+        let data = "if (a), if b".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        assert!( input.sep_by(",", if_statement).map_err(|e| e.is_fatal()).unwrap_err() );
+        Ok(())
+    }
+
+    #[test]
+    fn should_peek_without_consuming() -> Result<(), ParseError> {
+        let data = " 123 x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let number = input.peek(|i| i.parse_number())?;
+        assert_eq!( number.value, "123" );
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), " 123 x".to_string() ); 
+        assert!( input.peek(|i| i.parse_symbol()).is_err() );
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), " 123 x".to_string() ); 
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_not_followed_by() -> Result<(), ParseError> {
+        let data = " iffy".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        input.not_followed_by(|i| i.parse_number())?;
+        let e = input.not_followed_by(|i| i.parse_symbol());
+        assert!( matches!( e, Err(ParseError::ErrorAt(1, _)) ) );
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), " iffy".to_string() ); 
+        Ok(())
+    }
+
+    #[test]
+    fn should_peek_char_and_end() -> Result<(), ParseError> {
+        let data = " /* comment */ x ".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        assert_eq!( input.peek_char()?, Some('x') );
+        assert!( !input.at_end()? );
+        input.parse_symbol()?;
+        assert_eq!( input.peek_char()?, None );
+        assert!( input.at_end()? );
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), " ".to_string() ); 
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_many_till() -> Result<(), ParseError> {
+        let data = "1 2 3 end x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let items = input.many_till(|i| i.parse_number(), "end")?;
+        assert_eq!( items.len(), 3 );
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), " x".to_string() ); 
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_missing_many_till_terminator() -> Result<(), ParseError> {
+        let data = "1 2 x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let items = input.many_till(|i| i.parse_number(), "end");
+        match items {
+            Err(ParseError::ErrorAt(4, message)) => assert_eq!( message, "Expected `end` but found x" ),
+            _ => panic!( "expected error at 4" ),
+        }
+
+        let data = "1 2".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let items = input.many_till(|i| i.parse_number(), "end");
+        match items {
+            Err(ParseError::EndOfFile(message)) => assert_eq!( message, "Expected `end` but found end of file" ),
+            _ => panic!( "expected end of file" ),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_count() -> Result<(), ParseError> {
+        let data = "1 2 3".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let items = input.count(2, |i| i.parse_number())?;
+        assert_eq!( items.len(), 2 );
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), " 3".to_string() ); 
+        assert!( input.count(2, |i| i.parse_number()).is_err() );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_between() -> Result<(), ParseError> {
+        let data = "( 1 ) ( 2 ]".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let item = input.between("(", |i| i.parse_number(), ")")?;
+        assert_eq!( item.value, "1" );
+        let item = input.between("(", |i| i.parse_number(), ")");
+        match item {
+            Err(ParseError::ErrorAt(10, message)) => assert_eq!( message, "Expected `)` but found ]" ),
+            _ => panic!( "expected error at 10" ),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_skip_until() -> Result<(), ParseError> {
+        let data = "garbage 1 % ; x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        input.skip_until(";")?;
+        input.expect(";")?;
+        assert_eq!( input.data.into_iter().map(|(_,x)| x).collect::<String>(), " x".to_string() ); 
+        assert!( matches!( input.skip_until(";"), Err(ParseError::EndOfFile(_)) ) );
+        Ok(())
+    }
+
+    fn parameter_list(input : &mut Input) -> Result<Vec<PSym>, ParseError> {
+        input.context("while parsing parameter list", |i| i.delimited_list("(", ",", ")", |i| i.parse_symbol()))
+    }
+
+    fn function(input : &mut Input) -> Result<Vec<PSym>, ParseError> {
+        input.expect("fn")?;
+        input.context("while parsing function", |i| { i.parse_symbol()?; i.cut(parameter_list) })
+    }
+
+    #[test]
+    fn should_report_error_contexts() -> Result<(), ParseError> {
+        let data = "fn foo(a, 1)".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let e = input.choice(&[function, |i| Ok(vec![i.parse_symbol()?])]).unwrap_err();
+        assert!( e.is_fatal() );
+        assert_eq!( e.contexts(), vec!["while parsing parameter list", "while parsing function"] );
+        assert!( matches!( e.root(), ParseError::ErrorAt(10, _) ) );
+        assert_eq!( e.to_string(), "Error at 10: Encountered 1 in parse_symbol (while parsing parameter list → while parsing function)" );
+        Ok(())
+    }
+
+    fn nested(input : &mut Input) -> Result<usize, ParseError> {
+        input.choice(&[ |i| { i.expect("(")?; let n = nested(i)?; i.expect(")")?; Ok(n + 1) }
+                      , |_| Ok(0)
+                      ])
+    }
+
+    #[test]
+    fn should_limit_depth() -> Result<(), ParseError> {
+        let data = "((()))".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data).with_max_depth(4);
+        assert_eq!( nested(&mut input)?, 3 );
+
+        let data = "(((())))".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data).with_max_depth(4);
+        let e = nested(&mut input).unwrap_err();
+        assert!( matches!( e, ParseError::DepthExceeded(4) ) );
+        assert!( e.is_fatal() );
+
+        let data = "(".repeat(100_000).char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data).with_max_depth(500);
+        assert!( matches!( nested(&mut input), Err(ParseError::DepthExceeded(_)) ) );
+
+        let mut input = Input::new(&data);
+        assert!( matches!( nested(&mut input), Err(ParseError::DepthExceeded(_)) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_limit_fuel() -> Result<(), ParseError> {
+        let data = "1 2 3 4".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data).with_fuel(1000);
+        let numbers = input.zero_or_more(|i| i.parse_number())?;
+        assert_eq!( numbers.len(), 4 );
+        assert!( input.remaining_fuel().unwrap() < 1000 );
+
+        let mut input = Input::new(&data).with_fuel(10);
+        let e = input.zero_or_more(|i| i.parse_number()).unwrap_err();
+        assert!( matches!( e, ParseError::FuelExhausted(_) ) );
+        assert!( e.is_fatal() );
+        assert_eq!( input.remaining_fuel(), Some(0) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_detect_repetition_without_progress() -> Result<(), ParseError> {
+        let data = "1 2 x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let e = input.zero_or_more(|i| i.maybe(|i| i.parse_number())).unwrap_err();
+        assert!( matches!( e, ParseError::NoProgress(3) ) );
+        assert_eq!( e.to_string(), "Error at 3: Repeated parser succeeded without consuming input" );
+
+        let mut input = Input::new(&data);
+        let e = input.many_till(|i| i.maybe(|i| i.parse_number()), "y").unwrap_err();
+        assert!( matches!( e, ParseError::NoProgress(3) ) );
+        Ok(())
+    }
 }
@@ -0,0 +1,387 @@
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+
+use super::{error_at, Input, ParseError};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MemoStats {
+    pub hits : usize,
+    pub misses : usize,
+}
+
+impl MemoStats {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        }
+        else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+// Boxed answers are always Result<T, ParseError> for whichever T the rule produces.
+enum Answer {
+    Value(Box<dyn Any>),
+    LeftRecursion(usize),
+}
+
+struct MemoEntry<'a> {
+    answer : Answer,
+    data : &'a [(usize, char)],
+}
+
+// Left recursion is handled with the seed growing algorithm from Warth et al. "Packrat
+// Parsers Can Support Left Recursion".  A LeftRecursion marks a rule invocation that is
+// still in progress, and a Head tracks the rules involved in growing a seed at a position.
+struct LeftRecursion {
+    rule : &'static str,
+    seed : Box<dyn Any>,
+    head : Option<usize>,
+}
+
+struct Head {
+    rule : &'static str,
+    involved : HashSet<&'static str>,
+    eval : HashSet<&'static str>,
+}
+
+enum Recall {
+    Missing,
+    Found,
+    Fail,
+}
+
+pub(crate) struct MemoTable<'a> {
+    entries : HashMap<(&'static str, usize), MemoEntry<'a>>,
+    stats : HashMap<&'static str, MemoStats>,
+    lrs : Vec<LeftRecursion>,
+    lr_stack : Vec<usize>,
+    heads : Vec<Head>,
+    active_heads : HashMap<usize, usize>,
+}
+
+impl<'a> MemoTable<'a> {
+    pub(crate) fn new() -> MemoTable<'a> {
+        MemoTable { entries: HashMap::new()
+                  , stats: HashMap::new()
+                  , lrs: vec![]
+                  , lr_stack: vec![]
+                  , heads: vec![]
+                  , active_heads: HashMap::new()
+                  }
+    }
+}
+
+fn unbox<T : Clone + 'static>(rule : &'static str, answer : &dyn Any) -> Result<T, ParseError> {
+    match answer.downcast_ref::<Result<T, ParseError>>() {
+        Some(answer) => answer.clone(),
+        None => panic!("memo rule {} was used with more than one result type", rule),
+    }
+}
+
+impl<'a> Input<'a> {
+
+    /// Runs `parse` at most once per input position for the given rule name.  Later calls
+    /// at the same position replay the stored result and leave the input where the first
+    /// call left it.  Rules may be directly or indirectly left recursive as long as every
+    /// rule in the cycle goes through `memo`.
+    pub fn memo<T : Clone + 'static>(&mut self, rule : &'static str, parse : fn(&mut Input) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        let key = (rule, self.offset());
+
+        match self.recall(rule, parse) {
+            Recall::Fail => return Err(self.left_recursion_error(rule)),
+            Recall::Found => {
+                self.memo.stats.entry(rule).or_default().hits += 1;
+                let entry = &self.memo.entries[&key];
+                self.data = entry.data;
+                return match entry.answer {
+                    Answer::Value(ref answer) => unbox(rule, answer.as_ref()),
+                    Answer::LeftRecursion(lr) => {
+                        self.setup_left_recursion(lr);
+                        unbox(rule, self.memo.lrs[lr].seed.as_ref())
+                    },
+                };
+            },
+            Recall::Missing => { },
+        }
+
+        self.memo.stats.entry(rule).or_default().misses += 1;
+
+        let start = self.data;
+        let lr = self.memo.lrs.len();
+        let seed : Result<T, ParseError> = Err(self.left_recursion_error(rule));
+        self.memo.lrs.push(LeftRecursion { rule, seed: Box::new(seed), head: None });
+        self.memo.lr_stack.push(lr);
+        self.memo.entries.insert(key, MemoEntry { answer: Answer::LeftRecursion(lr), data: start });
+
+        let answer = self.apply(parse);
+
+        self.memo.lr_stack.pop();
+        let data = self.data;
+        let entry = self.memo.entries.get_mut(&key).expect("memo entry removed during evaluation");
+        entry.data = data;
+
+        if self.memo.lrs[lr].head.is_none() {
+            entry.answer = Answer::Value(Box::new(answer.clone()));
+            return answer;
+        }
+
+        self.memo.lrs[lr].seed = Box::new(answer);
+        self.left_recursion_answer(rule, lr, start, parse)
+    }
+
+    pub fn memo_stats(&self) -> MemoStats {
+        self.memo.stats.values().fold(MemoStats::default(), |acc, s| MemoStats { hits: acc.hits + s.hits
+                                                                               , misses: acc.misses + s.misses
+                                                                               })
+    }
+
+    pub fn rule_memo_stats(&self, rule : &str) -> MemoStats {
+        self.memo.stats.get(rule).copied().unwrap_or_default()
+    }
+
+    pub fn clear_memo(&mut self) {
+        self.memo = MemoTable::new();
+    }
+
+    fn left_recursion_error(&self, rule : &'static str) -> ParseError {
+        error_at(self.data, format!("Left recursion in {}", rule))
+    }
+
+    fn recall<T : Clone + 'static>(&mut self, rule : &'static str, parse : fn(&mut Input) -> Result<T, ParseError>) -> Recall {
+        let offset = self.offset();
+        let key = (rule, offset);
+        let exists = self.memo.entries.contains_key(&key);
+
+        let head = match self.memo.active_heads.get(&offset) {
+            None if exists => return Recall::Found,
+            None => return Recall::Missing,
+            Some(head) => &mut self.memo.heads[*head],
+        };
+
+        if !exists && head.rule != rule && !head.involved.contains(rule) {
+            return Recall::Fail;
+        }
+
+        if head.eval.remove(rule) {
+            self.memo.stats.entry(rule).or_default().misses += 1;
+            let answer = self.apply(parse);
+            self.memo.entries.insert(key, MemoEntry { answer: Answer::Value(Box::new(answer)), data: self.data });
+            return Recall::Found;
+        }
+
+        if exists {
+            Recall::Found
+        }
+        else {
+            Recall::Missing
+        }
+    }
+
+    fn setup_left_recursion(&mut self, lr : usize) {
+        let head = match self.memo.lrs[lr].head {
+            Some(head) => head,
+            None => {
+                let rule = self.memo.lrs[lr].rule;
+                self.memo.heads.push(Head { rule, involved: HashSet::new(), eval: HashSet::new() });
+                self.memo.lrs[lr].head = Some(self.memo.heads.len() - 1);
+                self.memo.heads.len() - 1
+            },
+        };
+
+        for s in self.memo.lr_stack.iter().rev() {
+            let s = &mut self.memo.lrs[*s];
+            if s.head == Some(head) {
+                break;
+            }
+            s.head = Some(head);
+            self.memo.heads[head].involved.insert(s.rule);
+        }
+    }
+
+    fn left_recursion_answer<T : Clone + 'static>( &mut self
+                                                 , rule : &'static str
+                                                 , lr : usize
+                                                 , start : &'a [(usize, char)]
+                                                 , parse : fn(&mut Input) -> Result<T, ParseError>
+                                                 ) -> Result<T, ParseError> {
+        let key = (rule, self.source.len() - start.len());
+        let head = self.memo.lrs[lr].head.expect("left recursion answer without a head");
+        let seed = unbox::<T>(rule, self.memo.lrs[lr].seed.as_ref());
+
+        if self.memo.heads[head].rule != rule {
+            return seed;
+        }
+
+        let entry = self.memo.entries.get_mut(&key).expect("memo entry removed during evaluation");
+        entry.answer = Answer::Value(Box::new(seed.clone()));
+
+        match seed {
+            Err(e) => Err(e),
+            Ok(_) => self.grow_left_recursion(key, start, head, parse),
+        }
+    }
+
+    fn grow_left_recursion<T : Clone + 'static>( &mut self
+                                               , key : (&'static str, usize)
+                                               , start : &'a [(usize, char)]
+                                               , head : usize
+                                               , parse : fn(&mut Input) -> Result<T, ParseError>
+                                               ) -> Result<T, ParseError> {
+        self.memo.active_heads.insert(key.1, head);
+
+        loop {
+            self.data = start;
+            self.memo.heads[head].eval = self.memo.heads[head].involved.clone();
+
+            let answer = self.apply(parse);
+
+            let data = self.data;
+            let entry = self.memo.entries.get_mut(&key).expect("memo entry removed during evaluation");
+            if answer.as_ref().is_err_and(|e| e.is_fatal()) {
+                entry.answer = Answer::Value(Box::new(answer));
+                entry.data = data;
+                break;
+            }
+            if answer.is_err() || data.len() >= entry.data.len() {
+                break;
+            }
+            entry.answer = Answer::Value(Box::new(answer));
+            entry.data = data;
+        }
+
+        self.memo.active_heads.remove(&key.1);
+
+        let entry = &self.memo.entries[&key];
+        self.data = entry.data;
+        match entry.answer {
+            Answer::Value(ref answer) => unbox(key.0, answer.as_ref()),
+            Answer::LeftRecursion(_) => unreachable!("grown left recursion without a value"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PSym;
+
+    fn number(input : &mut Input) -> Result<PSym, ParseError> {
+        input.memo("number", |i| i.parse_number())
+    }
+
+    #[test]
+    fn should_replay_memoized_rule() -> Result<(), ParseError> {
+        let data = "123 x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let rp = input.create_restore();
+        let first = number(&mut input)?;
+        input.restore(rp);
+        let second = number(&mut input)?;
+        assert_eq!( first.value, "123" );
+        assert_eq!( second.value, "123" );
+        assert_eq!( second.end, 2 );
+        assert_eq!( input.data.iter().map(|(_,x)| x).collect::<String>(), " x".to_string() );
+        assert_eq!( input.memo_stats(), MemoStats { hits: 1, misses: 1 } );
+        Ok(())
+    }
+
+    #[test]
+    fn should_memoize_failures() -> Result<(), ParseError> {
+        let data = "x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let item = input.choice(&[ |i : &mut Input| -> Result<PSym, ParseError> { number(i) }
+                                 , |i : &mut Input| -> Result<PSym, ParseError> { number(i) }
+                                 , |i : &mut Input| -> Result<PSym, ParseError> { i.parse_symbol() }
+                                 ])?;
+        assert_eq!( item.value, "x" );
+        assert_eq!( input.rule_memo_stats("number"), MemoStats { hits: 1, misses: 1 } );
+        assert_eq!( input.rule_memo_stats("number").hit_rate(), 0.5 );
+        Ok(())
+    }
+
+    #[test]
+    fn should_key_memo_by_position() -> Result<(), ParseError> {
+        let data = "1 2 3".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let numbers = input.zero_or_more(number)?;
+        assert_eq!( numbers.len(), 3 );
+        assert_eq!( numbers[2].value, "3" );
+        assert_eq!( input.memo_stats(), MemoStats { hits: 0, misses: 4 } );
+        Ok(())
+    }
+
+    fn sub(input : &mut Input) -> Result<String, ParseError> {
+        input.memo("sub", |i| i.choice(&[ |i| { 
+                                                let l = sub(i)?; 
+                                                i.expect("-")?; 
+                                                let r = i.parse_number()?; 
+                                                Ok(format!("({}-{})", l, r.value)) 
+                                              }
+                                        , |i| Ok(i.parse_number()?.value)
+                                        ]))
+    }
+
+    #[test]
+    fn should_parse_direct_left_recursion() -> Result<(), ParseError> {
+        let data = "1 - 2 - 3 x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let e = sub(&mut input)?;
+        assert_eq!( e, "((1-2)-3)" );
+        assert_eq!( input.data.iter().map(|(_,x)| x).collect::<String>(), " x".to_string() );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_direct_left_recursion_base_case() -> Result<(), ParseError> {
+        let data = "1".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let e = sub(&mut input)?;
+        assert_eq!( e, "1" );
+        assert_eq!( input.data.iter().map(|(_,x)| x).collect::<String>(), "".to_string() );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_direct_left_recursion_without_base_case() -> Result<(), ParseError> {
+        let data = "x".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let e = sub(&mut input);
+        assert!( e.is_err() );
+        assert_eq!( input.data.iter().map(|(_,x)| x).collect::<String>(), "x".to_string() );
+        Ok(())
+    }
+
+    fn call(input : &mut Input) -> Result<String, ParseError> {
+        input.memo("call", |i| i.choice(&[ |i| { 
+                                                 let f = primary(i)?; 
+                                                 i.expect("()")?; 
+                                                 Ok(format!("{}()", f)) 
+                                               }
+                                         , |i| Ok(i.parse_symbol()?.value)
+                                         ]))
+    }
+
+    fn primary(input : &mut Input) -> Result<String, ParseError> {
+        input.memo("primary", |i| i.choice(&[ |i| { 
+                                                    let f = call(i)?; 
+                                                    i.expect(".")?; 
+                                                    let s = i.parse_symbol()?; 
+                                                    Ok(format!("{}.{}", f, s.value)) 
+                                                  }
+                                            , call
+                                            ]))
+    }
+
+    #[test]
+    fn should_parse_indirect_left_recursion() -> Result<(), ParseError> {
+        let data = "a.b().c()()".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let e = primary(&mut input)?;
+        assert_eq!( e, "a.b().c()()" );
+        assert_eq!( input.data.iter().map(|(_,x)| x).collect::<String>(), "".to_string() );
+        Ok(())
+    }
+}
@@ -0,0 +1,571 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{error_at, Input, PSym, ParseError};
+
+/// A grammar loaded at runtime from PEG text such as
+///
+/// ```text
+/// list = "[" (item ("," item)*)? "]" ;
+/// item = number / symbol / string / list ;
+/// ```
+///
+/// Rules may be defined with `=`, `:=` or `<-`, alternatives are separated by `/` or `|`,
+/// and items may be followed by `?`, `*` or `+` or preceded by `&` or `!` for lookahead.
+/// `symbol`, `number` and `string` use the matching `parse_*` primitive unless the grammar
+/// defines a rule with that name.  The first rule is the start rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grammar {
+    pub rules : Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub name : String,
+    pub expr : Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(String),
+    Rule(String),
+    Seq(Vec<Expr>),
+    Choice(Vec<Expr>),
+    Optional(Box<Expr>),
+    ZeroOrMore(Box<Expr>),
+    OneOrMore(Box<Expr>),
+    And(Box<Expr>),
+    Not(Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Tree {
+    Node { rule : String, children : Vec<Tree> },
+    Leaf(PSym),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrammarError {
+    DuplicateRule(String),
+    UndefinedRule { rule : String, name : String },
+    UnreachableRule(String),
+    LeftRecursion(String),
+    NullableLoop(String),
+}
+
+impl std::fmt::Display for GrammarError {
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GrammarError::DuplicateRule(name) => write!(f, "Rule {} is defined more than once", name),
+            GrammarError::UndefinedRule { rule, name } => write!(f, "Rule {} refers to undefined rule {}", rule, name),
+            GrammarError::UnreachableRule(name) => write!(f, "Rule {} is unreachable from the start rule", name),
+            GrammarError::LeftRecursion(name) => write!(f, "Rule {} is left recursive", name),
+            GrammarError::NullableLoop(name) => write!(f, "Rule {} repeats an expression that can match nothing", name),
+        }
+    }
+}
+
+const BUILTINS : [&str; 3] = ["symbol", "number", "string"];
+
+fn parse_rule(input : &mut Input) -> Result<Rule, ParseError> {
+    let name = input.parse_symbol()?;
+    input.choice(&[ |i| i.expect(":=")
+                  , |i| i.expect("<-")
+                  , |i| i.expect("=")
+                  ])?;
+    let expr = input.cut(|i| {
+        let expr = parse_choice(i)?;
+        i.expect(";")?;
+        Ok(expr)
+    })?;
+    Ok(Rule { name: name.value, expr })
+}
+
+fn parse_choice(input : &mut Input) -> Result<Expr, ParseError> {
+    let mut alts = vec![parse_seq(input)?];
+    alts.append(&mut input.zero_or_more(|i| {
+        i.choice(&[|i| i.expect("/"), |i| i.expect("|")])?;
+        parse_seq(i)
+    })?);
+
+    if alts.len() == 1 {
+        Ok(alts.pop().unwrap())
+    }
+    else {
+        Ok(Expr::Choice(alts))
+    }
+}
+
+fn parse_seq(input : &mut Input) -> Result<Expr, ParseError> {
+    let mut items = input.one_or_more(parse_prefix)?;
+
+    if items.len() == 1 {
+        Ok(items.pop().unwrap())
+    }
+    else {
+        Ok(Expr::Seq(items))
+    }
+}
+
+fn parse_prefix(input : &mut Input) -> Result<Expr, ParseError> {
+    input.choice(&[ |i| { i.expect("&")?; Ok(Expr::And(Box::new(parse_suffix(i)?))) }
+                  , |i| { i.expect("!")?; Ok(Expr::Not(Box::new(parse_suffix(i)?))) }
+                  , parse_suffix
+                  ])
+}
+
+fn parse_suffix(input : &mut Input) -> Result<Expr, ParseError> {
+    let primary = parse_primary(input)?;
+    let suffix = input.maybe(|i| i.choice(&[ |i| { i.expect("?")?; Ok('?') }
+                                           , |i| { i.expect("*")?; Ok('*') }
+                                           , |i| { i.expect("+")?; Ok('+') }
+                                           ]))?;
+    match suffix {
+        Some('?') => Ok(Expr::Optional(Box::new(primary))),
+        Some('*') => Ok(Expr::ZeroOrMore(Box::new(primary))),
+        Some(_) => Ok(Expr::OneOrMore(Box::new(primary))),
+        None => Ok(primary),
+    }
+}
+
+fn parse_primary(input : &mut Input) -> Result<Expr, ParseError> {
+    input.choice(&[ |i| Ok(Expr::Literal(i.parse_string()?.value))
+                  , |i| {
+                        let name = i.parse_symbol()?;
+                        // a name followed by `=` starts the next rule
+                        i.not_followed_by(|i| i.choice(&[|i| i.expect(":="), |i| i.expect("<-"), |i| i.expect("=")]))?;
+                        Ok(Expr::Rule(name.value))
+                    }
+                  , |i| i.between("(", parse_choice, ")")
+                  ])
+}
+
+fn nullable(expr : &Expr, rules : &HashMap<&str, bool>) -> bool {
+    match expr {
+        Expr::Literal(s) => s.is_empty(),
+        Expr::Rule(name) => rules.get(name.as_str()).copied().unwrap_or(false),
+        Expr::Seq(items) => items.iter().all(|e| nullable(e, rules)),
+        Expr::Choice(alts) => alts.iter().any(|e| nullable(e, rules)),
+        Expr::Optional(_) | Expr::ZeroOrMore(_) | Expr::And(_) | Expr::Not(_) => true,
+        Expr::OneOrMore(e) => nullable(e, rules),
+    }
+}
+
+fn left_calls<'a>(expr : &'a Expr, rules : &HashMap<&str, bool>, calls : &mut HashSet<&'a str>) {
+    match expr {
+        Expr::Literal(_) => { },
+        Expr::Rule(name) => { calls.insert(name); },
+        Expr::Seq(items) => {
+            for e in items {
+                left_calls(e, rules, calls);
+                if !nullable(e, rules) {
+                    break;
+                }
+            }
+        },
+        Expr::Choice(alts) => alts.iter().for_each(|e| left_calls(e, rules, calls)),
+        Expr::Optional(e) | Expr::ZeroOrMore(e) | Expr::OneOrMore(e) | Expr::And(e) | Expr::Not(e) => left_calls(e, rules, calls),
+    }
+}
+
+fn all_calls<'a>(expr : &'a Expr, calls : &mut HashSet<&'a str>) {
+    match expr {
+        Expr::Literal(_) => { },
+        Expr::Rule(name) => { calls.insert(name); },
+        Expr::Seq(es) | Expr::Choice(es) => es.iter().for_each(|e| all_calls(e, calls)),
+        Expr::Optional(e) | Expr::ZeroOrMore(e) | Expr::OneOrMore(e) | Expr::And(e) | Expr::Not(e) => all_calls(e, calls),
+    }
+}
+
+fn has_nullable_loop(expr : &Expr, rules : &HashMap<&str, bool>) -> bool {
+    match expr {
+        Expr::Literal(_) | Expr::Rule(_) => false,
+        Expr::Seq(es) | Expr::Choice(es) => es.iter().any(|e| has_nullable_loop(e, rules)),
+        Expr::ZeroOrMore(e) | Expr::OneOrMore(e) => nullable(e, rules) || has_nullable_loop(e, rules),
+        Expr::Optional(e) | Expr::And(e) | Expr::Not(e) => has_nullable_loop(e, rules),
+    }
+}
+
+fn literal_sym(input : &mut Input, s : &str) -> Result<PSym, ParseError> {
+    input.clear()?;
+    let start = input.position();
+    input.expect(s)?;
+    let end = start + s.len() - s.chars().last().map_or(0, |c| c.len_utf8());
+    Ok(PSym { start, end, value: s.to_string() })
+}
+
+impl Grammar {
+
+    /// Reads at least one rule and then rules until the end of input.
+    pub fn load(input : &mut Input) -> Result<Grammar, ParseError> {
+        let rules = input.one_or_more(parse_rule)?;
+        input.expect_end()?;
+        Ok(Grammar { rules })
+    }
+
+    pub fn rule(&self, name : &str) -> Option<&Rule> {
+        self.rules.iter().find(|r| r.name == name)
+    }
+
+    /// Returns every problem found in the grammar.  A grammar with problems may still be
+    /// used, but undefined rules fail with a fatal error when called, and left recursion
+    /// and nullable loops fail with `DepthExceeded` and `NoProgress` when interpreted.
+    pub fn validate(&self) -> Vec<GrammarError> {
+        let mut errors = vec![];
+
+        let mut seen = HashSet::new();
+        for rule in self.rules.iter() {
+            if !seen.insert(rule.name.as_str()) {
+                errors.push(GrammarError::DuplicateRule(rule.name.clone()));
+            }
+        }
+
+        for rule in self.rules.iter() {
+            let mut calls = HashSet::new();
+            all_calls(&rule.expr, &mut calls);
+            let mut calls = calls.into_iter().collect::<Vec<_>>();
+            calls.sort();
+            for name in calls {
+                if !seen.contains(name) && !BUILTINS.contains(&name) {
+                    errors.push(GrammarError::UndefinedRule { rule: rule.name.clone(), name: name.to_string() });
+                }
+            }
+        }
+
+        if let Some(start) = self.rules.first() {
+            let mut reachable = HashSet::new();
+            let mut pending = vec![start.name.as_str()];
+            while let Some(name) = pending.pop() {
+                if reachable.insert(name) {
+                    if let Some(rule) = self.rule(name) {
+                        let mut calls = HashSet::new();
+                        all_calls(&rule.expr, &mut calls);
+                        pending.extend(calls);
+                    }
+                }
+            }
+            let mut reported = HashSet::new();
+            for rule in self.rules.iter() {
+                if !reachable.contains(rule.name.as_str()) && reported.insert(rule.name.as_str()) {
+                    errors.push(GrammarError::UnreachableRule(rule.name.clone()));
+                }
+            }
+        }
+
+        let mut nullables = HashMap::new();
+        loop {
+            let mut changed = false;
+            for rule in self.rules.iter() {
+                if !nullables.get(rule.name.as_str()).copied().unwrap_or(false) && nullable(&rule.expr, &nullables) {
+                    nullables.insert(rule.name.as_str(), true);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let left = self.rules.iter().map(|rule| {
+            let mut calls = HashSet::new();
+            left_calls(&rule.expr, &nullables, &mut calls);
+            (rule.name.as_str(), calls)
+        }).collect::<HashMap<_, _>>();
+
+        for rule in self.rules.iter() {
+            let mut visited = HashSet::new();
+            let mut pending = left[rule.name.as_str()].iter().copied().collect::<Vec<_>>();
+            while let Some(name) = pending.pop() {
+                if name == rule.name {
+                    errors.push(GrammarError::LeftRecursion(rule.name.clone()));
+                    break;
+                }
+                if visited.insert(name) {
+                    if let Some(calls) = left.get(name) {
+                        pending.extend(calls.iter().copied());
+                    }
+                }
+            }
+        }
+
+        for rule in self.rules.iter() {
+            if has_nullable_loop(&rule.expr, &nullables) {
+                errors.push(GrammarError::NullableLoop(rule.name.clone()));
+            }
+        }
+
+        errors
+    }
+
+    /// Interprets the start rule against `input`.  Each nested rule call and repetition
+    /// counts one level towards the input's depth limit.
+    pub fn parse(&self, input : &mut Input) -> Result<Tree, ParseError> {
+        match self.rules.first() {
+            Some(start) => self.parse_rule(&start.name, input),
+            None => Err(ParseError::Fatal(Box::new(error_at(input.data, "Grammar has no rules".to_string())))),
+        }
+    }
+
+    pub fn parse_rule(&self, name : &str, input : &mut Input) -> Result<Tree, ParseError> {
+        let mut trees = vec![];
+        self.apply(name, input, &mut trees)?;
+        Ok(trees.pop().expect("rule application without a tree"))
+    }
+
+    fn apply(&self, name : &str, input : &mut Input, out : &mut Vec<Tree>) -> Result<(), ParseError> {
+        let rule = match self.rule(name) {
+            Some(rule) => rule,
+            None => {
+                let sym = match name {
+                    "symbol" => input.parse_symbol()?,
+                    "number" => input.parse_number()?,
+                    "string" => input.parse_string()?,
+                    _ => return Err(ParseError::Fatal(Box::new(error_at(input.data, format!("Grammar refers to undefined rule {}", name))))),
+                };
+                out.push(Tree::Leaf(sym));
+                return Ok(());
+            },
+        };
+
+        let mut children = vec![];
+        input.enter()?;
+        let result = self.eval(&rule.expr, input, &mut children);
+        input.leave();
+        result?;
+
+        out.push(Tree::Node { rule: rule.name.clone(), children });
+        Ok(())
+    }
+
+    fn eval(&self, expr : &Expr, input : &mut Input, out : &mut Vec<Tree>) -> Result<(), ParseError> {
+        match expr {
+            Expr::Literal(s) => {
+                out.push(Tree::Leaf(literal_sym(input, s)?));
+                Ok(())
+            },
+            Expr::Rule(name) => self.apply(name, input, out),
+            Expr::Seq(items) => {
+                for e in items {
+                    self.eval(e, input, out)?;
+                }
+                Ok(())
+            },
+            Expr::Choice(alts) => self.choice(alts, input, out),
+            Expr::Optional(e) => {
+                self.attempt(e, input, out)?;
+                Ok(())
+            },
+            Expr::ZeroOrMore(e) => self.repeat(e, input, out),
+            Expr::OneOrMore(e) => {
+                self.eval(e, input, out)?;
+                self.repeat(e, input, out)
+            },
+            Expr::And(e) => self.and(e, input),
+            Expr::Not(e) => self.not(e, input),
+        }
+    }
+
+    // the larger cases live outside `eval` to keep its frame, which nests once per expression, small
+    fn choice(&self, alts : &[Expr], input : &mut Input, out : &mut Vec<Tree>) -> Result<(), ParseError> {
+        let rp = input.create_restore();
+        let len = out.len();
+        let mut error = None;
+        for e in alts {
+            match self.eval(e, input, out) {
+                Ok(()) => return Ok(()),
+                Err(e) if e.is_fatal() => return Err(e),
+                Err(e) => {
+                    error = Some(e);
+                    input.restore(rp);
+                    out.truncate(len);
+                },
+            }
+        }
+        Err(error.unwrap_or_else(|| error_at(input.data, "Encountered choice without alternatives".to_string())))
+    }
+
+    fn and(&self, expr : &Expr, input : &mut Input) -> Result<(), ParseError> {
+        let rp = input.create_restore();
+        self.eval(expr, input, &mut vec![])?;
+        input.restore(rp);
+        Ok(())
+    }
+
+    fn not(&self, expr : &Expr, input : &mut Input) -> Result<(), ParseError> {
+        let rp = input.create_restore();
+        let matched = self.attempt(expr, input, &mut vec![])?;
+        input.restore(rp);
+        if matched {
+            input.clear()?;
+            let e = ParseError::ErrorAt(input.position(), "Encountered input rejected by !".to_string());
+            input.restore(rp);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // evaluates `expr` and backtracks on failure, returning whether it matched
+    fn attempt(&self, expr : &Expr, input : &mut Input, out : &mut Vec<Tree>) -> Result<bool, ParseError> {
+        let rp = input.create_restore();
+        let len = out.len();
+        match self.eval(expr, input, out) {
+            Ok(()) => Ok(true),
+            Err(e) if e.is_fatal() => Err(e),
+            Err(_) => {
+                input.restore(rp);
+                out.truncate(len);
+                Ok(false)
+            },
+        }
+    }
+
+    fn repeat(&self, expr : &Expr, input : &mut Input, out : &mut Vec<Tree>) -> Result<(), ParseError> {
+        input.enter()?;
+        let result = self.repeat_items(expr, input, out);
+        input.leave();
+        result
+    }
+
+    fn repeat_items(&self, expr : &Expr, input : &mut Input, out : &mut Vec<Tree>) -> Result<(), ParseError> {
+        loop {
+            let before = input.data.len();
+            if !self.attempt(expr, input, out)? {
+                return Ok(());
+            }
+            if input.data.len() == before {
+                return Err(ParseError::NoProgress(input.position()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn load(s : &str) -> Result<Grammar, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        Grammar::load(&mut input)
+    }
+
+    fn leaves(tree : &Tree, out : &mut Vec<String>) {
+        match tree {
+            Tree::Leaf(sym) => out.push(sym.value.clone()),
+            Tree::Node { children, .. } => children.iter().for_each(|c| leaves(c, out)),
+        }
+    }
+
+    #[test]
+    fn should_load_grammar() -> Result<(), ParseError> {
+        let g = load(r#"
+            list <- "[" (item ("," item)*)? "]" ;
+            item := number | symbol / list ;
+            keyword = !item "x"+ &"y" ;
+        "#)?;
+        assert_eq!( g.rules.len(), 3 );
+        assert_eq!( g.rules[1], Rule { name: "item".to_string()
+                                     , expr: Expr::Choice(vec![ Expr::Rule("number".to_string())
+                                                              , Expr::Rule("symbol".to_string())
+                                                              , Expr::Rule("list".to_string())
+                                                              ])
+                                     } );
+        assert!( matches!( &g.rules[2].expr, Expr::Seq(items) if items.len() == 3 ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_to_load_bad_grammar() {
+        let e = load(r#" a = "x" ( ; "#).unwrap_err();
+        assert!( e.is_fatal() );
+    }
+
+    #[test]
+    fn should_validate_grammar() -> Result<(), ParseError> {
+        let g = load(r#"
+            start = expr missing ;
+            expr = expr "+" term / term ;
+            term = "x"* / loop ;
+            loop = ("a"?)* ;
+            unused = "u" ;
+            unused = "v" ;
+        "#)?;
+        let errors = g.validate();
+        assert_eq!( errors, vec![ GrammarError::DuplicateRule("unused".to_string())
+                                , GrammarError::UndefinedRule { rule: "start".to_string(), name: "missing".to_string() }
+                                , GrammarError::UnreachableRule("unused".to_string())
+                                , GrammarError::LeftRecursion("expr".to_string())
+                                , GrammarError::NullableLoop("loop".to_string())
+                                ] );
+        assert!( load(r#" a = "(" a ")" / b ; b = number ; "#)?.validate().is_empty() );
+        Ok(())
+    }
+
+    #[test]
+    fn should_interpret_grammar() -> Result<(), ParseError> {
+        let g = load(r#"
+            list = "[" (item ("," item)*)? "]" ;
+            item = number / symbol / list ;
+        "#)?;
+        let data = "[1, a, [b, []]]".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let tree = g.parse(&mut input)?;
+        input.expect_end()?;
+
+        let mut values = vec![];
+        leaves(&tree, &mut values);
+        assert_eq!( values.join(" "), "[ 1 , a , [ b , [ ] ] ]" );
+
+        match tree {
+            Tree::Node { rule, children } => {
+                assert_eq!( rule, "list" );
+                assert!( matches!( &children[1], Tree::Node { rule, .. } if rule == "item" ) );
+                assert!( matches!( &children[0], Tree::Leaf(PSym { start: 0, end: 0, .. }) ) );
+            },
+            _ => panic!( "expected node" ),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_interpret_lookahead() -> Result<(), ParseError> {
+        let g = load(r#" start = !"end" symbol &"." ; "#)?;
+
+        let data = "foo.".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        g.parse(&mut input)?;
+        assert_eq!( input.data.iter().map(|(_,x)| x).collect::<String>(), ".".to_string() );
+
+        let data = "end.".char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        assert!( g.parse(&mut input).is_err() );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_instead_of_panicking() -> Result<(), ParseError> {
+        assert!( load("").is_err() );
+        assert!( Grammar { rules: vec![] }.parse(&mut Input::new(&[])).unwrap_err().is_fatal() );
+
+        let data = "xxx".char_indices().collect::<Vec<(usize, char)>>();
+        let e = load(" a <- b ; ")?.parse(&mut Input::new(&data)).unwrap_err();
+        assert!( matches!( e.root(), ParseError::ErrorAt(0, m) if m.ends_with("undefined rule b") ) );
+
+        let e = load(r#" a <- a "x" / "x" ; "#)?.parse(&mut Input::new(&data)).unwrap_err();
+        assert!( matches!( e, ParseError::DepthExceeded(_) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_interpret_deep_nesting() -> Result<(), ParseError> {
+        let g = load(r#"
+            list = "[" (item ("," item)*)? "]" ;
+            item = number / list ;
+        "#)?;
+        let text = format!("{}1{}", "[".repeat(100), "]".repeat(100));
+        let data = text.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        g.parse(&mut input)?;
+        input.expect_end()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,319 @@
+use super::{Input, ParseError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Assoc {
+    Left,
+    Right,
+    NonAssoc,
+}
+
+enum Prefix<T> {
+    Unary { op : &'static str, bp : u32, build : fn(T) -> T },
+    Group { open : &'static str, close : &'static str },
+}
+
+enum Trailing<T> {
+    Infix { op : &'static str, precedence : u32, assoc : Assoc, build : fn(T, T) -> T },
+    Postfix { op : &'static str, bp : u32, build : fn(T) -> T },
+    Ternary { op : &'static str, sep : &'static str, bp : u32, build : fn(T, T, T) -> T },
+    Call { open : &'static str, close : &'static str, bp : u32, build : fn(T, Vec<T>) -> T },
+    Index { open : &'static str, close : &'static str, bp : u32, build : fn(T, T) -> T },
+}
+
+impl<T> Prefix<T> {
+    fn op(&self) -> &'static str {
+        match self {
+            Prefix::Unary { op, .. } => op,
+            Prefix::Group { open, .. } => open,
+        }
+    }
+}
+
+impl<T> Trailing<T> {
+    fn op(&self) -> &'static str {
+        match self {
+            Trailing::Infix { op, .. } => op,
+            Trailing::Postfix { op, .. } => op,
+            Trailing::Ternary { op, .. } => op,
+            Trailing::Call { open, .. } => open,
+            Trailing::Index { open, .. } => open,
+        }
+    }
+
+    fn left_bp(&self) -> u32 {
+        match self {
+            Trailing::Infix { precedence, assoc: Assoc::Right, .. } => precedence * 2 + 1,
+            Trailing::Infix { precedence, .. } => precedence * 2,
+            Trailing::Postfix { bp, .. } => *bp,
+            Trailing::Ternary { bp, .. } => *bp + 1,
+            Trailing::Call { bp, .. } => *bp,
+            Trailing::Index { bp, .. } => *bp,
+        }
+    }
+}
+
+/// Operator precedence parser.  Each operator is registered with a precedence where higher
+/// numbers bind tighter; `atom` parses everything that isn't an operator.
+pub struct Pratt<T> {
+    atom : fn(&mut Input) -> Result<T, ParseError>,
+    prefix : Vec<Prefix<T>>,
+    trailing : Vec<Trailing<T>>,
+}
+
+fn expect_operator(input : &mut Input, op : &str) -> Result<(), ParseError> {
+    input.expect(op)?;
+
+    let is_word = |c : char| c.is_alphanumeric() || c == '_';
+    match input.data {
+        [(i, x), ..] if op.ends_with(is_word) && is_word(*x) =>
+            Err(ParseError::ErrorAt(*i, format!("Expected operator {} but found {}", op, x))),
+        _ => Ok(()),
+    }
+}
+
+impl<T> Pratt<T> {
+
+    pub fn new(atom : fn(&mut Input) -> Result<T, ParseError>) -> Pratt<T> {
+        Pratt { atom, prefix: vec![], trailing: vec![] }
+    }
+
+    pub fn prefix(mut self, op : &'static str, precedence : u32, build : fn(T) -> T) -> Pratt<T> {
+        self.add_prefix(Prefix::Unary { op, bp: precedence * 2 + 1, build });
+        self
+    }
+
+    pub fn group(mut self, open : &'static str, close : &'static str) -> Pratt<T> {
+        self.add_prefix(Prefix::Group { open, close });
+        self
+    }
+
+    pub fn infix(mut self, op : &'static str, precedence : u32, assoc : Assoc, build : fn(T, T) -> T) -> Pratt<T> {
+        self.add_trailing(Trailing::Infix { op, precedence, assoc, build });
+        self
+    }
+
+    pub fn postfix(mut self, op : &'static str, precedence : u32, build : fn(T) -> T) -> Pratt<T> {
+        self.add_trailing(Trailing::Postfix { op, bp: precedence * 2, build });
+        self
+    }
+
+    /// Right associative `cond op middle sep right` such as `a ? b : c`.  The middle
+    /// expression is parsed at the lowest precedence.
+    pub fn ternary(mut self, op : &'static str, sep : &'static str, precedence : u32, build : fn(T, T, T) -> T) -> Pratt<T> {
+        self.add_trailing(Trailing::Ternary { op, sep, bp: precedence * 2, build });
+        self
+    }
+
+    /// Postfix call such as `f(a, b)` with comma separated arguments.
+    pub fn call(mut self, open : &'static str, close : &'static str, precedence : u32, build : fn(T, Vec<T>) -> T) -> Pratt<T> {
+        self.add_trailing(Trailing::Call { open, close, bp: precedence * 2, build });
+        self
+    }
+
+    /// Postfix index such as `a[i]`.
+    pub fn index(mut self, open : &'static str, close : &'static str, precedence : u32, build : fn(T, T) -> T) -> Pratt<T> {
+        self.add_trailing(Trailing::Index { open, close, bp: precedence * 2, build });
+        self
+    }
+
+    // operators are kept longest first so that `<=` is tried before `<`
+    fn add_prefix(&mut self, prefix : Prefix<T>) {
+        self.prefix.push(prefix);
+        self.prefix.sort_by_key(|p| std::cmp::Reverse(p.op().len()));
+    }
+
+    fn add_trailing(&mut self, trailing : Trailing<T>) {
+        self.trailing.push(trailing);
+        self.trailing.sort_by_key(|t| std::cmp::Reverse(t.op().len()));
+    }
+
+    pub fn parse(&self, input : &mut Input) -> Result<T, ParseError> {
+        self.parse_bp(input, 0)
+    }
+
+    fn parse_prefix(&self, input : &mut Input) -> Result<T, ParseError> {
+        for prefix in self.prefix.iter() {
+            let rp = input.create_restore();
+            if expect_operator(input, prefix.op()).is_err() {
+                input.restore(rp);
+                continue;
+            }
+
+            return match prefix {
+                Prefix::Unary { bp, build, .. } => Ok(build(self.parse_bp(input, *bp)?)),
+                Prefix::Group { close, .. } => {
+                    let e = self.parse_bp(input, 0)?;
+                    input.expect(close)?;
+                    Ok(e)
+                },
+            };
+        }
+
+        input.apply(self.atom)
+    }
+
+    fn parse_trailing(&self, input : &mut Input) -> Option<&Trailing<T>> {
+        for trailing in self.trailing.iter() {
+            let rp = input.create_restore();
+            match expect_operator(input, trailing.op()) {
+                Ok(()) => return Some(trailing),
+                Err(_) => input.restore(rp),
+            }
+        }
+        None
+    }
+
+    fn parse_bp(&self, input : &mut Input, min_bp : u32) -> Result<T, ParseError> {
+        input.enter()?;
+        let result = self.parse_operators(input, min_bp);
+        input.leave();
+        result
+    }
+
+    fn parse_operators(&self, input : &mut Input, min_bp : u32) -> Result<T, ParseError> {
+        let mut lhs = self.parse_prefix(input)?;
+        let mut non_assoc = None;
+
+        loop {
+            let rp = input.create_restore();
+            let trailing = match self.parse_trailing(input) {
+                Some(trailing) => trailing,
+                None => break,
+            };
+
+            if trailing.left_bp() < min_bp {
+                input.restore(rp);
+                break;
+            }
+
+            lhs = match trailing {
+                Trailing::Infix { op, precedence, assoc, build } => {
+                    if *assoc == Assoc::NonAssoc {
+                        if non_assoc == Some(*precedence) {
+                            input.restore(rp);
+                            input.clear()?;
+                            let i = input.data.first().map_or(0, |(i, _)| *i);
+                            return Err(ParseError::ErrorAt(i, format!("Non-associative operator {} cannot be chained", op)));
+                        }
+                        non_assoc = Some(*precedence);
+                    }
+                    else {
+                        non_assoc = None;
+                    }
+                    let right_bp = match assoc {
+                        Assoc::Right => precedence * 2,
+                        _ => precedence * 2 + 1,
+                    };
+                    build(lhs, self.parse_bp(input, right_bp)?)
+                },
+                Trailing::Postfix { build, .. } => build(lhs),
+                Trailing::Ternary { sep, bp, build, .. } => {
+                    let middle = self.parse_bp(input, 0)?;
+                    input.expect(sep)?;
+                    let right = self.parse_bp(input, *bp)?;
+                    build(lhs, middle, right)
+                },
+                Trailing::Call { close, build, .. } => {
+                    let mut args = vec![];
+                    let rp = input.create_restore();
+                    if input.expect(close).is_err() {
+                        input.restore(rp);
+                        args.push(self.parse_bp(input, 0)?);
+                        while input.expect(",").is_ok() {
+                            args.push(self.parse_bp(input, 0)?);
+                        }
+                        input.expect(close)?;
+                    }
+                    build(lhs, args)
+                },
+                Trailing::Index { close, build, .. } => {
+                    let index = self.parse_bp(input, 0)?;
+                    input.expect(close)?;
+                    build(lhs, index)
+                },
+            };
+        }
+
+        Ok(lhs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn atom(input : &mut Input) -> Result<String, ParseError> {
+        input.choice(&[ |i| Ok(i.parse_number()?.value)
+                      , |i| Ok(i.parse_symbol()?.value)
+                      ])
+    }
+
+    fn parser() -> Pratt<String> {
+        Pratt::new(atom)
+            .infix("=", 1, Assoc::Right, |l, r| format!("({} = {})", l, r))
+            .ternary("?", ":", 2, |c, l, r| format!("({} ? {} : {})", c, l, r))
+            .infix("<", 3, Assoc::NonAssoc, |l, r| format!("({} < {})", l, r))
+            .infix("<=", 3, Assoc::NonAssoc, |l, r| format!("({} <= {})", l, r))
+            .infix("+", 4, Assoc::Left, |l, r| format!("({} + {})", l, r))
+            .infix("-", 4, Assoc::Left, |l, r| format!("({} - {})", l, r))
+            .infix("*", 5, Assoc::Left, |l, r| format!("({} * {})", l, r))
+            .infix("^", 7, Assoc::Right, |l, r| format!("({} ^ {})", l, r))
+            .prefix("-", 6, |e| format!("(-{})", e))
+            .prefix("not", 6, |e| format!("(not {})", e))
+            .postfix("!", 8, |e| format!("({}!)", e))
+            .call("(", ")", 9, |f, args| format!("{}({})", f, args.join(", ")))
+            .index("[", "]", 9, |a, i| format!("{}[{}]", a, i))
+            .group("(", ")")
+    }
+
+    fn parse(s : &str) -> Result<String, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let e = parser().parse(&mut input)?;
+        input.expect_end()?;
+        Ok(e)
+    }
+
+    #[test]
+    fn should_parse_left_and_right_associativity() -> Result<(), ParseError> {
+        assert_eq!( parse("1 - 2 - 3")?, "((1 - 2) - 3)" );
+        assert_eq!( parse("1 ^ 2 ^ 3")?, "(1 ^ (2 ^ 3))" );
+        assert_eq!( parse("a = b = 1 + 2 * 3")?, "(a = (b = (1 + (2 * 3))))" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_prefix_and_postfix() -> Result<(), ParseError> {
+        assert_eq!( parse("- a ^ b")?, "(-(a ^ b))" );
+        assert_eq!( parse("-a!")?, "(-(a!))" );
+        assert_eq!( parse("not note")?, "(not note)" );
+        assert_eq!( parse("(1 + 2) * 3")?, "((1 + 2) * 3)" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_mixfix() -> Result<(), ParseError> {
+        assert_eq!( parse("a ? b : c ? d : e")?, "(a ? b : (c ? d : e))" );
+        assert_eq!( parse("f(1, g(), x[2 + 3])[0]")?, "f(1, g(), x[(2 + 3)])[0]" );
+        assert_eq!( parse("a <= b + 1")?, "(a <= (b + 1))" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_chained_non_associative_operators() {
+        let e = parse("a < b <= c");
+        assert!( matches!( e, Err(ParseError::ErrorAt(6, _)) ) );
+    }
+
+    #[test]
+    fn should_fail_missing_close() {
+        let e = parse("f(1, 2");
+        assert!( matches!( e, Err(ParseError::EndOfFile(_)) ) );
+    }
+
+    #[test]
+    fn should_limit_depth_by_default() {
+        assert!( matches!( parse(&format!("{}1", "-".repeat(20_000))), Err(ParseError::DepthExceeded(_)) ) );
+        assert!( matches!( parse(&format!("{}1", "(".repeat(20_000))), Err(ParseError::DepthExceeded(_)) ) );
+    }
+}
@@ -0,0 +1,262 @@
+/// A document for the pretty printer in the style of Wadler's "A prettier printer".  Each
+/// group is printed on one line if it fits in the width, otherwise its line breaks become
+/// newlines indented by the enclosing `nest`s.
+#[derive(Debug, Clone)]
+pub struct Doc(Node);
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    // printed as the text when flat and as a newline otherwise
+    Line(&'static str),
+    HardLine,
+    Concat(Vec<Doc>),
+    Nest(usize, Box<Doc>),
+    Group(Box<Doc>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Quotes and escapes `s` so that `parse_string` gives back `s`.
+pub fn quote_string(s : &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\0' => out.push_str("\\0"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Formats `n` so that `parse_number` gives back the same value.  Infinities and NaN
+/// have no syntax and give `None`.
+pub fn format_number(n : f64) -> Option<String> {
+    if n.is_finite() {
+        // `Display` never uses an exponent and prints the shortest digits that round-trip
+        Some(format!("{}", n))
+    }
+    else {
+        None
+    }
+}
+
+impl Doc {
+    pub fn nil() -> Doc {
+        Doc(Node::Concat(vec![]))
+    }
+
+    /// Text without newlines.
+    pub fn text<S : Into<String>>(s : S) -> Doc {
+        let s = s.into();
+        assert!( !s.contains('\n'), "Doc::text must not contain newlines, use Doc::hardline" );
+        Doc(Node::Text(s))
+    }
+
+    /// A space when flat, otherwise a newline.
+    pub fn line() -> Doc {
+        Doc(Node::Line(" "))
+    }
+
+    /// Nothing when flat, otherwise a newline.
+    pub fn softline() -> Doc {
+        Doc(Node::Line(""))
+    }
+
+    /// Always a newline.  Groups containing one never print flat.
+    pub fn hardline() -> Doc {
+        Doc(Node::HardLine)
+    }
+
+    pub fn string(s : &str) -> Doc {
+        Doc::text(quote_string(s))
+    }
+
+    /// `None` for NaN and the infinities, which have no syntax.
+    pub fn number(n : f64) -> Option<Doc> {
+        format_number(n).map(Doc::text)
+    }
+
+    pub fn concat<I : IntoIterator<Item = Doc>>(docs : I) -> Doc {
+        Doc(Node::Concat(docs.into_iter().collect()))
+    }
+
+    pub fn join<I : IntoIterator<Item = Doc>>(docs : I, sep : Doc) -> Doc {
+        let mut result = vec![];
+        for (index, doc) in docs.into_iter().enumerate() {
+            if index > 0 {
+                result.push(sep.clone());
+            }
+            result.push(doc);
+        }
+        Doc::concat(result)
+    }
+
+    /// The printing counterpart of `Input::delimited_list`: the items on one line, or one
+    /// item per indented line with the closing delimiter on its own line.
+    pub fn delimited_list<I : IntoIterator<Item = Doc>>(open : &str, sep : &str, close : &str, items : I) -> Doc {
+        let items = Doc::join(items, Doc::text(sep).append(Doc::line()));
+        Doc::text(open).append(Doc::softline().append(items).nest(4))
+                       .append(Doc::softline())
+                       .append(Doc::text(close))
+                       .group()
+    }
+
+    pub fn append(self, other : Doc) -> Doc {
+        match self.0 {
+            Node::Concat(mut docs) => {
+                docs.push(other);
+                Doc(Node::Concat(docs))
+            },
+            node => Doc(Node::Concat(vec![Doc(node), other])),
+        }
+    }
+
+    /// Indents the lines broken inside this document by `indent` more spaces.
+    pub fn nest(self, indent : usize) -> Doc {
+        Doc(Node::Nest(indent, Box::new(self)))
+    }
+
+    pub fn group(self) -> Doc {
+        Doc(Node::Group(Box::new(self)))
+    }
+
+    pub fn render(&self, width : usize) -> String {
+        let mut out = String::new();
+        let mut column = 0;
+        let mut stack = vec![(0, Mode::Break, self)];
+
+        while let Some((indent, mode, doc)) = stack.pop() {
+            match &doc.0 {
+                Node::Text(s) => {
+                    out.push_str(s);
+                    column += s.chars().count();
+                },
+                Node::Line(flat) if mode == Mode::Flat => {
+                    out.push_str(flat);
+                    column += flat.chars().count();
+                },
+                Node::Line(_) | Node::HardLine => {
+                    while out.ends_with(' ') {
+                        out.pop();
+                    }
+                    out.push('\n');
+                    out.extend(std::iter::repeat_n(' ', indent));
+                    column = indent;
+                },
+                Node::Concat(docs) => stack.extend(docs.iter().rev().map(|d| (indent, mode, d))),
+                Node::Nest(n, d) => stack.push((indent + n, mode, d)),
+                Node::Group(d) if mode == Mode::Flat => stack.push((indent, Mode::Flat, d)),
+                Node::Group(d) => {
+                    let flat = fits(width.saturating_sub(column), d, &stack);
+                    stack.push((indent, if flat { Mode::Flat } else { Mode::Break }, d));
+                },
+            }
+        }
+
+        out
+    }
+}
+
+// whether `doc` printed flat, and what follows it up to the next possible line break,
+// fits in `width` columns
+fn fits(width : usize, doc : &Doc, rest : &[(usize, Mode, &Doc)]) -> bool {
+    let mut width = width as isize;
+    let mut stack = vec![(Mode::Flat, doc)];
+    let mut rest = rest.iter().rev();
+
+    loop {
+        let (mode, doc) = match stack.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some((_, mode, doc)) => (*mode, *doc),
+                None => return true,
+            },
+        };
+
+        match &doc.0 {
+            Node::Text(s) => width -= s.chars().count() as isize,
+            Node::Line(flat) if mode == Mode::Flat => width -= flat.chars().count() as isize,
+            Node::Line(_) => return true,
+            Node::HardLine => return mode == Mode::Break,
+            Node::Concat(docs) => stack.extend(docs.iter().rev().map(|d| (mode, d))),
+            Node::Nest(_, d) | Node::Group(d) => stack.push((mode, d)),
+        }
+
+        if width < 0 {
+            return false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Input, ParseError};
+
+    fn call(name : &str, args : Vec<Doc>) -> Doc {
+        Doc::text(name).append(Doc::delimited_list("(", ",", ")", args))
+    }
+
+    #[test]
+    fn should_render_flat_when_it_fits() {
+        let doc = call("f", vec![Doc::number(1.0).unwrap(), Doc::string("a"), call("g", vec![])]);
+        assert_eq!( doc.render(80), r#"f(1, "a", g())"# );
+    }
+
+    #[test]
+    fn should_break_groups_that_do_not_fit() {
+        let inner = call("inner", vec![Doc::text("alpha"), Doc::text("beta")]);
+        let doc = call("outer", vec![inner, Doc::text("gamma")]);
+        assert_eq!( doc.render(24), "outer(\n    inner(alpha, beta),\n    gamma\n)" );
+        assert_eq!( doc.render(10), "outer(\n    inner(\n        alpha,\n        beta\n    ),\n    gamma\n)" );
+    }
+
+    #[test]
+    fn should_break_groups_with_hard_lines() {
+        let doc = Doc::text("{").append(Doc::line().append(Doc::text("a")).append(Doc::hardline()).append(Doc::text("b")).nest(2))
+                                .append(Doc::line())
+                                .append(Doc::text("}"))
+                                .group();
+        assert_eq!( doc.render(80), "{\n  a\n  b\n}" );
+    }
+
+    #[test]
+    fn should_round_trip_strings() -> Result<(), ParseError> {
+        for s in &["", "plain", "quote \" and \\ slash", "lines\r\n\ttab\0", "λ /* not a comment */"] {
+            let printed = quote_string(s);
+            let data = printed.char_indices().collect::<Vec<(usize, char)>>();
+            let mut input = Input::new(&data);
+            assert_eq!( input.parse_string()?.value, *s );
+            input.expect_end()?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_round_trip_numbers() -> Result<(), ParseError> {
+        for n in &[0.0, -0.0, 1.0, -42.0, 0.1, 3.25e-7, 1.5e300, f64::MAX, f64::MIN_POSITIVE] {
+            let printed = format_number(*n).unwrap();
+            let data = printed.char_indices().collect::<Vec<(usize, char)>>();
+            let mut input = Input::new(&data);
+            let parsed = input.parse_number()?;
+            input.expect_end()?;
+            assert_eq!( parsed.value.parse::<f64>().unwrap().to_bits(), n.to_bits() );
+        }
+        assert!( format_number(f64::NAN).is_none() );
+        assert!( format_number(f64::INFINITY).is_none() );
+        assert!( Doc::number(f64::NEG_INFINITY).is_none() );
+        Ok(())
+    }
+}
@@ -0,0 +1,667 @@
+use std::ops::Range;
+
+use super::{Input, ParseError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GroupKind {
+    /// `(x)` with its 1-based index.
+    Capture(usize),
+    /// `(?<name>x)` or `(?P<name>x)`, which is also numbered.
+    Named(usize, String),
+    /// `(?:x)`
+    NonCapturing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClassItem {
+    Char(char),
+    /// Both ends included.
+    Range(char, char),
+    /// `\d`, or `\D` when negated.
+    Digit(bool),
+    /// `\w`, or `\W` when negated.
+    Word(bool),
+    /// `\s`, or `\S` when negated.
+    Space(bool),
+}
+
+#[derive(Debug, Clone)]
+pub enum RegexNode {
+    /// Matches the empty string, as in `a|` or `()`.
+    Empty,
+    Literal(char),
+    /// `.`, which doesn't match `\n`.
+    Any,
+    /// `[...]`, or a single class escape such as `\d`.
+    Class { negated : bool, items : Vec<ClassItem> },
+    /// `^`
+    Start,
+    /// `$`
+    End,
+    /// `\b`, or `\B` when false.
+    WordBoundary(bool),
+    Group(GroupKind, Box<Regex>),
+    /// `max` is `None` for no upper bound, and `greedy` is false after a trailing `?`.
+    Repeat { inner : Box<Regex>, min : u32, max : Option<u32>, greedy : bool },
+    Concat(Vec<Regex>),
+    Alternation(Vec<Regex>),
+}
+
+/// A regular expression syntax tree with the byte indices of the first and last
+/// characters of each node.  An empty node has `start == end` at its position.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    pub start : usize,
+    pub end : usize,
+    pub value : RegexNode,
+}
+
+impl<'a> Input<'a> {
+
+    /// Parses a regular expression until the end of input.  Supports alternation,
+    /// capturing, named and non-capturing groups, `*` `+` `?` `{n}` `{n,}` `{n,m}` with an
+    /// optional `?` for lazy repetition, `.`, `^`, `$`, `\b`, `\B`, classes with ranges and
+    /// the escapes `\d \w \s \D \W \S \n \r \t \f \v \0 \xHH` plus escaped punctuation.
+    pub fn parse_regex(&mut self) -> Result<Regex, ParseError> {
+        let mut groups = Groups { count: 0, names: vec![] };
+        let regex = alternation(self, &mut groups)?;
+        match self.data {
+            [] => Ok(regex),
+            [(i, _), ..] => Err(ParseError::ErrorAt(*i, "Encountered ) without matching (".to_string())),
+        }
+    }
+}
+
+struct Groups {
+    count : usize,
+    names : Vec<String>,
+}
+
+fn empty(input : &Input) -> Regex {
+    let position = input.position();
+    Regex { start: position, end: position, value: RegexNode::Empty }
+}
+
+fn alternation(input : &mut Input, groups : &mut Groups) -> Result<Regex, ParseError> {
+    let mut alternatives = vec![concat(input, groups)?];
+    while let [(_, '|'), rest @ ..] = input.data {
+        input.data = rest;
+        alternatives.push(concat(input, groups)?);
+    }
+    if alternatives.len() == 1 {
+        return Ok(alternatives.pop().unwrap());
+    }
+    let (start, end) = (alternatives[0].start, alternatives[alternatives.len() - 1].end);
+    Ok(Regex { start, end, value: RegexNode::Alternation(alternatives) })
+}
+
+fn concat(input : &mut Input, groups : &mut Groups) -> Result<Regex, ParseError> {
+    let mut items = vec![];
+    loop {
+        match input.data {
+            [] | [(_, '|' | ')'), ..] => break,
+            _ => {
+                let atom = atom(input, groups)?;
+                items.push(quantified(input, atom)?);
+            },
+        }
+    }
+    match items.len() {
+        0 => Ok(empty(input)),
+        1 => Ok(items.pop().unwrap()),
+        _ => {
+            let (start, end) = (items[0].start, items[items.len() - 1].end);
+            Ok(Regex { start, end, value: RegexNode::Concat(items) })
+        },
+    }
+}
+
+// a number for `{n,m}`, or `None` when there are no digits
+fn count(input : &mut Input) -> Result<Option<u32>, ParseError> {
+    let digits = input.data.iter().take_while(|(_, c)| c.is_ascii_digit()).count();
+    if digits == 0 {
+        return Ok(None);
+    }
+    let start = input.position();
+    let text = input.data[.. digits].iter().map(|(_, c)| c).collect::<String>();
+    input.data = &input.data[digits ..];
+    text.parse().map(Some).map_err(|_| ParseError::ErrorAt(start, format!("Encountered repetition count {} that is too large", text)))
+}
+
+fn quantified(input : &mut Input, atom : Regex) -> Result<Regex, ParseError> {
+    let (min, max) = match input.data {
+        [(_, '*'), rest @ ..] => { input.data = rest; (0, None) },
+        [(_, '+'), rest @ ..] => { input.data = rest; (1, None) },
+        [(_, '?'), rest @ ..] => { input.data = rest; (0, Some(1)) },
+        [(open, '{'), rest @ ..] => {
+            let open = *open;
+            input.data = rest;
+            let min = count(input)?.ok_or_else(|| input.unexpected("repetition count"))?;
+            let max = match input.data {
+                [(_, ','), rest @ ..] => {
+                    input.data = rest;
+                    count(input)?
+                },
+                _ => Some(min),
+            };
+            match input.data {
+                [(_, '}'), rest @ ..] => input.data = rest,
+                _ => return Err(input.unexpected("`}`")),
+            }
+            if max.is_some_and(|max| max < min) {
+                return Err(ParseError::ErrorAt(open, format!("Encountered repetition with maximum less than minimum {}", min)));
+            }
+            (min, max)
+        },
+        _ => return Ok(atom),
+    };
+
+    let greedy = match input.data {
+        [(_, '?'), rest @ ..] => {
+            input.data = rest;
+            false
+        },
+        _ => true,
+    };
+    if let [(i, '*' | '+' | '?' | '{'), ..] = input.data {
+        return Err(ParseError::ErrorAt(*i, "Encountered quantifier with nothing to repeat".to_string()));
+    }
+
+    let end = input.position() - 1;
+    Ok(Regex { start: atom.start, end, value: RegexNode::Repeat { inner: Box::new(atom), min, max, greedy } })
+}
+
+fn atom(input : &mut Input, groups : &mut Groups) -> Result<Regex, ParseError> {
+    let start = input.position();
+    let single = |input : &mut Input, value| {
+        input.data = &input.data[1 ..];
+        Ok(Regex { start, end: start, value })
+    };
+    match input.data {
+        [(_, '('), ..] => {
+            input.enter()?;
+            let result = group(input, groups);
+            input.leave();
+            result
+        },
+        [(_, '['), ..] => class(input),
+        [(_, '\\'), ..] => escape(input),
+        [(_, '.'), ..] => single(input, RegexNode::Any),
+        [(_, '^'), ..] => single(input, RegexNode::Start),
+        [(_, '$'), ..] => single(input, RegexNode::End),
+        [(i, '*' | '+' | '?' | '{'), ..] => Err(ParseError::ErrorAt(*i, "Encountered quantifier with nothing to repeat".to_string())),
+        [(_, c), ..] => { let c = *c; single(input, RegexNode::Literal(c)) },
+        [] => Err(input.unexpected("regular expression")),
+    }
+}
+
+fn group(input : &mut Input, groups : &mut Groups) -> Result<Regex, ParseError> {
+    let start = input.position();
+    input.data = &input.data[1 ..];
+
+    let kind = match input.data {
+        [(_, '?'), (_, ':'), rest @ ..] => {
+            input.data = rest;
+            GroupKind::NonCapturing
+        },
+        [(_, '?'), (_, 'P'), (_, '<'), rest @ ..] | [(_, '?'), (_, '<'), rest @ ..] => {
+            input.data = rest;
+            let name_start = input.position();
+            let len = input.data.iter().take_while(|(_, c)| c.is_alphanumeric() || *c == '_').count();
+            let name = input.data[.. len].iter().map(|(_, c)| c).collect::<String>();
+            input.data = &input.data[len ..];
+            match input.data {
+                [(_, '>'), rest @ ..] if len > 0 => input.data = rest,
+                _ => return Err(input.unexpected("group name followed by `>`")),
+            }
+            if groups.names.contains(&name) {
+                return Err(ParseError::ErrorAt(name_start, format!("Encountered duplicate group name {}", name)));
+            }
+            groups.count += 1;
+            groups.names.push(name.clone());
+            GroupKind::Named(groups.count, name)
+        },
+        [(i, '?'), ..] => return Err(ParseError::ErrorAt(*i, "Expected `:` or `<name>` after (?".to_string())),
+        _ => {
+            groups.count += 1;
+            GroupKind::Capture(groups.count)
+        },
+    };
+
+    let inner = alternation(input, groups)?;
+    match input.data {
+        [(end, ')'), rest @ ..] => {
+            let end = *end;
+            input.data = rest;
+            Ok(Regex { start, end, value: RegexNode::Group(kind, Box::new(inner)) })
+        },
+        _ => Err(input.unexpected("`)`")),
+    }
+}
+
+enum Escaped {
+    Char(char),
+    Class(ClassItem),
+    Boundary(bool),
+}
+
+// reads the escape after `\`, leaving the index of its last character
+fn escaped(input : &mut Input) -> Result<(Escaped, usize), ParseError> {
+    let (i, c) = match input.data {
+        [(_, '\\'), (i, c), rest @ ..] => {
+            input.data = rest;
+            (*i, *c)
+        },
+        _ => {
+            input.data = &input.data[1 ..];
+            return Err(input.unexpected("escaped character"));
+        },
+    };
+    let escaped = match c {
+        'd' | 'D' => Escaped::Class(ClassItem::Digit(c == 'D')),
+        'w' | 'W' => Escaped::Class(ClassItem::Word(c == 'W')),
+        's' | 'S' => Escaped::Class(ClassItem::Space(c == 'S')),
+        'b' | 'B' => Escaped::Boundary(c == 'b'),
+        'n' => Escaped::Char('\n'),
+        'r' => Escaped::Char('\r'),
+        't' => Escaped::Char('\t'),
+        'f' => Escaped::Char('\u{c}'),
+        'v' => Escaped::Char('\u{b}'),
+        '0' => Escaped::Char('\0'),
+        'x' => {
+            let hex = input.data.iter().take(2).take_while(|(_, c)| c.is_ascii_hexdigit()).map(|(_, c)| c).collect::<String>();
+            if hex.len() < 2 {
+                input.data = &input.data[hex.len() ..];
+                return Err(input.unexpected("hex digit"));
+            }
+            let end = input.data[1].0;
+            input.data = &input.data[2 ..];
+            return Ok((Escaped::Char(u8::from_str_radix(&hex, 16).unwrap() as char), end));
+        },
+        c if !c.is_alphanumeric() => Escaped::Char(c),
+        c => return Err(ParseError::ErrorAt(i, format!("Encountered unknown escape \\{}", c))),
+    };
+    Ok((escaped, i))
+}
+
+fn escape(input : &mut Input) -> Result<Regex, ParseError> {
+    let start = input.position();
+    let (escaped, end) = escaped(input)?;
+    let value = match escaped {
+        Escaped::Char(c) => RegexNode::Literal(c),
+        Escaped::Class(item) => RegexNode::Class { negated: false, items: vec![item] },
+        Escaped::Boundary(b) => RegexNode::WordBoundary(b),
+    };
+    Ok(Regex { start, end, value })
+}
+
+// a character in a class, where `\b` is not allowed
+fn class_char(input : &mut Input) -> Result<Escaped, ParseError> {
+    match input.data {
+        [(i, '\\'), (_, 'b' | 'B'), ..] => Err(ParseError::ErrorAt(*i, "Encountered word boundary in character class".to_string())),
+        [(_, '\\'), ..] => Ok(escaped(input)?.0),
+        [(_, c), rest @ ..] => {
+            input.data = rest;
+            Ok(Escaped::Char(*c))
+        },
+        [] => Err(input.unexpected("`]`")),
+    }
+}
+
+fn class(input : &mut Input) -> Result<Regex, ParseError> {
+    let start = input.position();
+    input.data = &input.data[1 ..];
+    let negated = match input.data {
+        [(_, '^'), rest @ ..] => {
+            input.data = rest;
+            true
+        },
+        _ => false,
+    };
+
+    let mut items = vec![];
+    loop {
+        match input.data {
+            // a `]` first in the class is a literal
+            [(end, ']'), rest @ ..] if !items.is_empty() => {
+                let end = *end;
+                input.data = rest;
+                return Ok(Regex { start, end, value: RegexNode::Class { negated, items } });
+            },
+            _ => { },
+        }
+
+        let range_start = input.position();
+        let low = class_char(input)?;
+        match (low, input.data) {
+            (Escaped::Char(low), [(_, '-'), (_, c), ..]) if *c != ']' => {
+                input.data = &input.data[1 ..];
+                match class_char(input)? {
+                    Escaped::Char(high) if low <= high => items.push(ClassItem::Range(low, high)),
+                    Escaped::Char(_) => return Err(ParseError::ErrorAt(range_start, "Encountered character range out of order".to_string())),
+                    _ => return Err(ParseError::ErrorAt(range_start, "Encountered class escape as the end of a range".to_string())),
+                }
+            },
+            (Escaped::Char(c), _) => items.push(ClassItem::Char(c)),
+            (Escaped::Class(item), _) => items.push(item),
+            (Escaped::Boundary(_), _) => unreachable!("rejected by class_char"),
+        }
+    }
+}
+
+fn is_word(c : char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl ClassItem {
+    fn matches(&self, c : char) -> bool {
+        match *self {
+            ClassItem::Char(x) => c == x,
+            ClassItem::Range(low, high) => low <= c && c <= high,
+            ClassItem::Digit(negated) => c.is_ascii_digit() != negated,
+            ClassItem::Word(negated) => is_word(c) != negated,
+            ClassItem::Space(negated) => c.is_whitespace() != negated,
+        }
+    }
+}
+
+// the tree compiled into a program so that matching backtracks with a stack on the heap
+// instead of one call per matched character
+#[derive(Clone, Copy)]
+enum Inst<'r> {
+    Char(char),
+    Any,
+    Class(bool, &'r [ClassItem]),
+    Start,
+    End,
+    WordBoundary(bool),
+    Save(usize),
+    // tries the first instruction, then the second
+    Split(usize, usize),
+    Jump(usize),
+    // sets a repetition's counter to zero
+    Reset(usize),
+    // chooses between another iteration, which follows, and `exit`
+    Loop { counter : usize, min : u32, max : Option<u32>, greedy : bool, exit : usize },
+    // remembers where an iteration started
+    Mark(usize),
+    // ends an iteration and goes back to its `Loop`
+    Next { counter : usize, mark : usize, min : u32, start : usize },
+    Match,
+}
+
+// entries are popped when an alternative fails, undoing changes back to the last `Try`
+enum Job {
+    Try(usize, usize),
+    Slot(usize, Option<usize>),
+    Register(usize, usize),
+}
+
+struct Program<'r> {
+    insts : Vec<Inst<'r>>,
+    slots : usize,
+    registers : usize,
+}
+
+impl<'r> Program<'r> {
+    fn new(regex : &'r Regex) -> Program<'r> {
+        let mut program = Program { insts: vec![], slots: 2 * regex.groups() + 2, registers: 0 };
+        program.compile(regex);
+        program.insts.push(Inst::Match);
+        program
+    }
+
+    fn push(&mut self, inst : Inst<'r>) -> usize {
+        self.insts.push(inst);
+        self.insts.len() - 1
+    }
+
+    fn compile(&mut self, node : &'r Regex) {
+        match &node.value {
+            RegexNode::Empty => { },
+            RegexNode::Literal(c) => { self.push(Inst::Char(*c)); },
+            RegexNode::Any => { self.push(Inst::Any); },
+            RegexNode::Class { negated, items } => { self.push(Inst::Class(*negated, items)); },
+            RegexNode::Start => { self.push(Inst::Start); },
+            RegexNode::End => { self.push(Inst::End); },
+            RegexNode::WordBoundary(expected) => { self.push(Inst::WordBoundary(*expected)); },
+            RegexNode::Group(GroupKind::NonCapturing, inner) => self.compile(inner),
+            RegexNode::Group(GroupKind::Capture(index) | GroupKind::Named(index, _), inner) => {
+                self.push(Inst::Save(2 * index));
+                self.compile(inner);
+                self.push(Inst::Save(2 * index + 1));
+            },
+            RegexNode::Repeat { inner, min, max, greedy } => {
+                let (counter, mark) = (self.registers, self.registers + 1);
+                self.registers += 2;
+                self.push(Inst::Reset(counter));
+                let start = self.push(Inst::Jump(0));
+                self.push(Inst::Mark(mark));
+                self.compile(inner);
+                self.push(Inst::Next { counter, mark, min: *min, start });
+                let exit = self.insts.len();
+                self.insts[start] = Inst::Loop { counter, min: *min, max: *max, greedy: *greedy, exit };
+            },
+            RegexNode::Concat(items) => items.iter().for_each(|item| self.compile(item)),
+            RegexNode::Alternation(alternatives) => {
+                let mut jumps = vec![];
+                let (last, rest) = alternatives.split_last().expect("alternation has alternatives");
+                for alternative in rest {
+                    let split = self.push(Inst::Jump(0));
+                    self.compile(alternative);
+                    jumps.push(self.push(Inst::Jump(0)));
+                    self.insts[split] = Inst::Split(split + 1, self.insts.len());
+                }
+                self.compile(last);
+                let end = self.insts.len();
+                for jump in jumps {
+                    self.insts[jump] = Inst::Jump(end);
+                }
+            },
+        }
+    }
+
+    // the slots of the first match starting at `start`, in order of preference
+    fn run(&self, text : &[char], start : usize) -> Option<Vec<Option<usize>>> {
+        let mut slots = vec![None; self.slots];
+        let mut registers = vec![0; self.registers];
+        let mut jobs = vec![Job::Try(0, start)];
+
+        while let Some(job) = jobs.pop() {
+            let (mut pc, mut pos) = match job {
+                Job::Try(pc, pos) => (pc, pos),
+                Job::Slot(i, old) => {
+                    slots[i] = old;
+                    continue;
+                },
+                Job::Register(i, old) => {
+                    registers[i] = old;
+                    continue;
+                },
+            };
+
+            loop {
+                let next = text.get(pos).copied();
+                match self.insts[pc] {
+                    Inst::Char(c) if next == Some(c) => pos += 1,
+                    Inst::Any if next.is_some_and(|c| c != '\n') => pos += 1,
+                    Inst::Class(negated, items) if next.is_some_and(|c| items.iter().any(|i| i.matches(c)) != negated) => pos += 1,
+                    Inst::Char(_) | Inst::Any | Inst::Class(..) => break,
+                    Inst::Start if pos == 0 => { },
+                    Inst::End if next.is_none() => { },
+                    Inst::Start | Inst::End => break,
+                    Inst::WordBoundary(expected) => {
+                        let before = pos > 0 && is_word(text[pos - 1]);
+                        let after = next.is_some_and(is_word);
+                        if (before != after) != expected {
+                            break;
+                        }
+                    },
+                    Inst::Save(i) => {
+                        jobs.push(Job::Slot(i, slots[i]));
+                        slots[i] = Some(pos);
+                    },
+                    Inst::Split(first, second) => {
+                        jobs.push(Job::Try(second, pos));
+                        pc = first;
+                        continue;
+                    },
+                    Inst::Jump(target) => {
+                        pc = target;
+                        continue;
+                    },
+                    Inst::Reset(counter) => {
+                        jobs.push(Job::Register(counter, registers[counter]));
+                        registers[counter] = 0;
+                    },
+                    Inst::Loop { counter, min, max, greedy, exit } => {
+                        let count = registers[counter] as u32;
+                        // greedy repetition tries another iteration before stopping, lazy the reverse
+                        if count >= min && max.is_some_and(|max| count >= max) {
+                            pc = exit;
+                            continue;
+                        }
+                        if count >= min && greedy {
+                            jobs.push(Job::Try(exit, pos));
+                        }
+                        else if count >= min {
+                            jobs.push(Job::Try(pc + 1, pos));
+                            pc = exit;
+                            continue;
+                        }
+                    },
+                    Inst::Mark(mark) => {
+                        jobs.push(Job::Register(mark, registers[mark]));
+                        registers[mark] = pos;
+                    },
+                    Inst::Next { counter, mark, min, start } => {
+                        // stop an iteration that matched nothing from looping forever
+                        if pos == registers[mark] && registers[counter] as u32 >= min {
+                            break;
+                        }
+                        jobs.push(Job::Register(counter, registers[counter]));
+                        registers[counter] += 1;
+                        pc = start;
+                        continue;
+                    },
+                    Inst::Match => {
+                        slots[0] = Some(start);
+                        slots[1] = Some(pos);
+                        return Some(slots);
+                    },
+                }
+                pc += 1;
+            }
+        }
+        None
+    }
+}
+
+impl Regex {
+    /// The number of capturing groups, named or not.
+    pub fn groups(&self) -> usize {
+        match &self.value {
+            RegexNode::Group(kind, inner) => inner.groups() + if *kind == GroupKind::NonCapturing { 0 } else { 1 },
+            RegexNode::Repeat { inner, .. } => inner.groups(),
+            RegexNode::Concat(items) | RegexNode::Alternation(items) => items.iter().map(Regex::groups).sum(),
+            _ => 0,
+        }
+    }
+
+    /// Finds the leftmost match in `text` with a backtracking search.  Gives the byte range
+    /// of the whole match followed by the range of each group, or `None` for groups that
+    /// didn't take part in the match.
+    pub fn find(&self, text : &str) -> Option<Vec<Option<Range<usize>>>> {
+        let indices = text.char_indices().map(|(i, _)| i).chain(std::iter::once(text.len())).collect::<Vec<_>>();
+        let chars = text.chars().collect::<Vec<_>>();
+        let program = Program::new(self);
+
+        let slots = (0 .. indices.len()).find_map(|start| program.run(&chars, start))?;
+        Some(slots.chunks(2).map(|pair| match pair {
+            [Some(s), Some(e)] => Some(indices[*s] .. indices[*e]),
+            _ => None,
+        }).collect())
+    }
+
+    pub fn is_match(&self, text : &str) -> bool {
+        self.find(text).is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s : &str) -> Result<Regex, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        input.parse_regex()
+    }
+
+    fn find(pattern : &str, text : &str) -> Option<Vec<Option<String>>> {
+        let caps = parse(pattern).unwrap().find(text)?;
+        Some(caps.into_iter().map(|c| c.map(|r| text[r].to_string())).collect())
+    }
+
+    fn strings(caps : &[&str]) -> Option<Vec<Option<String>>> {
+        Some(caps.iter().map(|c| if *c == "-" { None } else { Some(c.to_string()) }).collect())
+    }
+
+    #[test]
+    fn should_parse_tree() -> Result<(), ParseError> {
+        let regex = parse("ab|(?<year>\\d{4})*?[^a-z_]")?;
+        let alternatives = match &regex.value {
+            RegexNode::Alternation(alternatives) => alternatives,
+            other => panic!( "unexpected {:?}", other ),
+        };
+        assert_eq!( (alternatives[1].start, alternatives[1].end), (3, 25) );
+        match &alternatives[1].value {
+            RegexNode::Concat(items) => {
+                assert!( matches!( &items[0].value, RegexNode::Repeat { min: 0, max: None, greedy: false, .. } ) );
+                assert_eq!( (items[0].start, items[0].end), (3, 18) );
+                assert!( matches!( &items[1].value, RegexNode::Class { negated: true, items } if items == &[ClassItem::Range('a', 'z'), ClassItem::Char('_')] ) );
+            },
+            other => panic!( "unexpected {:?}", other ),
+        }
+        assert_eq!( regex.groups(), 1 );
+        Ok(())
+    }
+
+    #[test]
+    fn should_match() {
+        assert_eq!( find("(\\w+)@(\\w+)\\.com", "mail bob@example.com now"), strings(&["bob@example.com", "bob", "example"]) );
+        assert_eq!( find("a(b)?c|a(d)", "ad"), strings(&["ad", "-", "d"]) );
+        assert_eq!( find("<.+>", "<a><b>"), strings(&["<a><b>"]) );
+        assert_eq!( find("<.+?>", "<a><b>"), strings(&["<a>"]) );
+        assert_eq!( find("^x{2,3}$", "xxxx"), None );
+        assert_eq!( find("^(?:x{2,3})+$", "xxxxx"), strings(&["xxxxx"]) );
+        assert_eq!( find("\\bcat\\b", "concat cat"), strings(&["cat"]) );
+        assert_eq!( find("[]a-]+", "x]-a]"), strings(&["]-a]"]) );
+        assert_eq!( find("(a*)*b", "aab"), strings(&["aab", "aa"]) );
+        assert_eq!( find("é.", "xéλ"), strings(&["éλ"]) );
+        assert!( parse("(?P<n>x)\\x41").unwrap().is_match("xA") );
+    }
+
+    #[test]
+    fn should_match_long_subjects() {
+        let long = "ab".repeat(100_000);
+        assert!( parse("^(?:ab)*$").unwrap().is_match(&long) );
+        assert!( parse("a*").unwrap().is_match(&"a".repeat(100_000)) );
+        assert_eq!( find("(b?)*x$", &format!("{}x", "b".repeat(100_000))).map(|m| m[1].clone()), Some(Some("b".to_string())) );
+    }
+
+    #[test]
+    fn should_report_errors() {
+        assert!( matches!( parse("a)"), Err(ParseError::ErrorAt(1, _)) ) );
+        assert!( matches!( parse("(a"), Err(ParseError::EndOfFile(_)) ) );
+        assert!( matches!( parse("*a"), Err(ParseError::ErrorAt(0, _)) ) );
+        assert!( matches!( parse("a**"), Err(ParseError::ErrorAt(2, _)) ) );
+        assert!( matches!( parse("a{3,1}"), Err(ParseError::ErrorAt(1, _)) ) );
+        assert!( matches!( parse("a{x}"), Err(ParseError::ErrorAt(2, _)) ) );
+        assert!( matches!( parse("[z-a]"), Err(ParseError::ErrorAt(1, _)) ) );
+        assert!( matches!( parse("[abc"), Err(ParseError::EndOfFile(_)) ) );
+        assert!( matches!( parse("\\q"), Err(ParseError::ErrorAt(1, _)) ) );
+        assert!( matches!( parse("(?<a>x)(?<a>y)"), Err(ParseError::ErrorAt(10, _)) ) );
+        assert!( matches!( parse("(?=x)"), Err(ParseError::ErrorAt(1, _)) ) );
+    }
+}
@@ -0,0 +1,262 @@
+use super::{Input, ParseError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuoteKind {
+    /// `'x`
+    Quote,
+    /// `` `x ``
+    Quasiquote,
+    /// `,x`
+    Unquote,
+    /// `,@x`
+    UnquoteSplicing,
+}
+
+#[derive(Debug, Clone)]
+pub enum SExprValue {
+    Symbol(String),
+    /// The number as written.
+    Number(String),
+    String(String),
+    List(Vec<SExpr>),
+    /// `(a b . c)` with the items before the dot and the tail after it.
+    Dotted(Vec<SExpr>, Box<SExpr>),
+    Quoted(QuoteKind, Box<SExpr>),
+}
+
+/// An s-expression with the byte indices of its first and last characters.
+#[derive(Debug, Clone)]
+pub struct SExpr {
+    pub start : usize,
+    pub end : usize,
+    pub value : SExprValue,
+}
+
+impl SExpr {
+    pub fn as_symbol(&self) -> Option<&str> {
+        match &self.value {
+            SExprValue::Symbol(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[SExpr]> {
+        match &self.value {
+            SExprValue::List(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Input<'a> {
+
+    /// Reads one s-expression along with the whitespace and `;` comments around it.
+    pub fn parse_sexpr(&mut self) -> Result<SExpr, ParseError> {
+        skip_whitespace(self);
+        let form = form(self)?;
+        skip_whitespace(self);
+        Ok(form)
+    }
+
+    /// Reads s-expressions until the end of input.
+    pub fn parse_sexprs(&mut self) -> Result<Vec<SExpr>, ParseError> {
+        let mut forms = vec![];
+        skip_whitespace(self);
+        while !self.data.is_empty() {
+            forms.push(self.parse_sexpr()?);
+        }
+        Ok(forms)
+    }
+}
+
+// `clear` isn't used because `/*` can start a symbol
+fn skip_whitespace(input : &mut Input) {
+    loop {
+        match input.data {
+            [(_, c), rest @ ..] if c.is_whitespace() => input.data = rest,
+            [(_, ';'), rest @ ..] => {
+                let mut d = rest;
+                while let [(_, c), r @ ..] = d {
+                    if *c == '\n' {
+                        break;
+                    }
+                    d = r;
+                }
+                input.data = d;
+            },
+            _ => return,
+        }
+    }
+}
+
+fn is_delimiter(c : char) -> bool {
+    c.is_whitespace() || "()\"';`,".contains(c)
+}
+
+fn form(input : &mut Input) -> Result<SExpr, ParseError> {
+    match input.data {
+        [] => Err(input.unexpected("s-expression")),
+        [(_, '('), ..] => input.apply(list),
+        [(i, ')'), ..] => Err(ParseError::ErrorAt(*i, "Encountered ) without matching (".to_string())),
+        [(_, '"'), ..] => {
+            let s = input.parse_string()?;
+            Ok(SExpr { start: s.start, end: s.end, value: SExprValue::String(s.value) })
+        },
+        [(i, '\''), rest @ ..] => quoted(input, *i, rest, QuoteKind::Quote),
+        [(i, '`'), rest @ ..] => quoted(input, *i, rest, QuoteKind::Quasiquote),
+        [(i, ','), (_, '@'), rest @ ..] => quoted(input, *i, rest, QuoteKind::UnquoteSplicing),
+        [(i, ','), rest @ ..] => quoted(input, *i, rest, QuoteKind::Unquote),
+        _ => atom(input),
+    }
+}
+
+fn quoted<'a>(input : &mut Input<'a>, start : usize, rest : &'a [(usize, char)], kind : QuoteKind) -> Result<SExpr, ParseError> {
+    input.data = rest;
+    skip_whitespace(input);
+    // quotes nest like lists do, so they count towards the depth limit
+    let inner = input.apply(form)?;
+    Ok(SExpr { start, end: inner.end, value: SExprValue::Quoted(kind, Box::new(inner)) })
+}
+
+// a run of non-delimiters is a number if `parse_number` reads all of it, otherwise a symbol
+fn atom(input : &mut Input) -> Result<SExpr, ParseError> {
+    let token = input.data.iter().take_while(|(_, c)| !is_delimiter(*c)).count();
+    let (start, end) = (input.data[0].0, input.data[token - 1].0);
+
+    if input.data[.. token].iter().any(|(_, c)| c.is_ascii_digit()) {
+        let rp = input.create_restore();
+        if let Ok(n) = input.parse_number() {
+            if input.data.len() == rp.data.len() - token {
+                return Ok(SExpr { start, end, value: SExprValue::Number(n.value) });
+            }
+        }
+        input.restore(rp);
+    }
+
+    let value = input.data[.. token].iter().map(|(_, c)| c).collect::<String>();
+    input.data = &input.data[token ..];
+    Ok(SExpr { start, end, value: SExprValue::Symbol(value) })
+}
+
+fn is_dot(input : &Input) -> bool {
+    match input.data {
+        [(_, '.')] => true,
+        [(_, '.'), (_, c), ..] => is_delimiter(*c),
+        _ => false,
+    }
+}
+
+fn list(input : &mut Input) -> Result<SExpr, ParseError> {
+    let start = input.position();
+    input.raw_expect("(")?;
+
+    let mut items = vec![];
+    let mut tail = None;
+    loop {
+        skip_whitespace(input);
+        match input.data {
+            [] => return Err(input.unexpected("`)`")),
+            [(end, ')'), rest @ ..] => {
+                let end = *end;
+                input.data = rest;
+                let value = match tail {
+                    Some(tail) => SExprValue::Dotted(items, Box::new(tail)),
+                    None => SExprValue::List(items),
+                };
+                return Ok(SExpr { start, end, value });
+            },
+            [(i, _), ..] if tail.is_some() =>
+                return Err(ParseError::ErrorAt(*i, "Expected ) after the tail of a dotted list".to_string())),
+            [(i, _), rest @ ..] if is_dot(input) => {
+                if items.is_empty() {
+                    return Err(ParseError::ErrorAt(*i, "Expected an item before . in dotted list".to_string()));
+                }
+                input.data = rest;
+                skip_whitespace(input);
+                if let [(_, ')'), ..] = input.data {
+                    return Err(input.unexpected("s-expression after ."));
+                }
+                tail = Some(form(input)?);
+            },
+            _ => items.push(form(input)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read(s : &str) -> Result<Vec<SExpr>, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        input.parse_sexprs()
+    }
+
+    // prints the forms back in a normalized way
+    fn show(e : &SExpr) -> String {
+        let join = |items : &[SExpr]| items.iter().map(show).collect::<Vec<_>>().join(" ");
+        match &e.value {
+            SExprValue::Symbol(s) => s.clone(),
+            SExprValue::Number(n) => format!("#{}", n),
+            SExprValue::String(s) => format!("{:?}", s),
+            SExprValue::List(items) => format!("({})", join(items)),
+            SExprValue::Dotted(items, tail) => format!("({} . {})", join(items), show(tail)),
+            SExprValue::Quoted(kind, inner) => format!("{:?}:{}", kind, show(inner)),
+        }
+    }
+
+    #[test]
+    fn should_read_forms() -> Result<(), ParseError> {
+        let forms = read(r#"
+            ; a comment
+            (define (square x) (* x x))  ; trailing
+            (list 1 -2.5 1e3 "a\nb" 1+ - ->x /*weird*/ ...)
+            (a . b) (a b . (c))
+        "#)?;
+        let shown = forms.iter().map(show).collect::<Vec<_>>();
+        assert_eq!( shown, vec![ "(define (square x) (* x x))"
+                               , r#"(list #1 #-2.5 #1e3 "a\nb" 1+ - ->x /*weird*/ ...)"#
+                               , "(a . b)"
+                               , "(a b . (c))"
+                               ] );
+        Ok(())
+    }
+
+    #[test]
+    fn should_read_quote_shorthand() -> Result<(), ParseError> {
+        let forms = read("'a `(b ,c ,@d) ' e")?;
+        let shown = forms.iter().map(show).collect::<Vec<_>>();
+        assert_eq!( shown, vec!["Quote:a", "Quasiquote:(b Unquote:c UnquoteSplicing:d)", "Quote:e"] );
+        assert_eq!( (forms[1].start, forms[1].end), (3, 13) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_give_spans() -> Result<(), ParseError> {
+        let forms = read("(foo \"λ\" 12)")?;
+        let items = forms[0].as_list().unwrap();
+        assert_eq!( (forms[0].start, forms[0].end), (0, 12) );
+        assert_eq!( items[0].as_symbol(), Some("foo") );
+        assert_eq!( (items[0].start, items[0].end), (1, 3) );
+        assert_eq!( (items[1].start, items[1].end), (5, 8) );
+        assert_eq!( (items[2].start, items[2].end), (10, 11) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_on_bad_forms() {
+        assert!( matches!( read("(a b"), Err(ParseError::EndOfFile(_)) ) );
+        assert!( matches!( read("a)"), Err(ParseError::ErrorAt(1, _)) ) );
+        assert!( matches!( read("( . a)"), Err(ParseError::ErrorAt(2, _)) ) );
+        assert!( matches!( read("(a . b c)"), Err(ParseError::ErrorAt(7, _)) ) );
+        assert!( read("(a .)").is_err() );
+        assert!( read("'").is_err() );
+    }
+
+    #[test]
+    fn should_limit_depth() {
+        assert!( matches!( read(&"(".repeat(100_000)), Err(ParseError::DepthExceeded(_)) ) );
+        assert!( matches!( read(&format!("{}a", "'".repeat(200_000))), Err(ParseError::DepthExceeded(_)) ) );
+    }
+}
@@ -0,0 +1,275 @@
+use super::{Input, PSym, ParseError};
+
+#[derive(Debug, Clone)]
+pub enum WordPart {
+    /// Text with the quotes and escapes removed.
+    Literal(String),
+    /// `$NAME` or `${NAME}`, spanning the whole reference.
+    Variable(PSym),
+}
+
+/// A word with the byte indices of its first and last characters, including quotes.
+#[derive(Debug, Clone)]
+pub struct ShellWord {
+    pub start : usize,
+    pub end : usize,
+    pub parts : Vec<WordPart>,
+}
+
+impl ShellWord {
+    /// The text of the word when it has no variable references.
+    pub fn literal(&self) -> Option<String> {
+        self.parts.iter().map(|p| match p {
+            WordPart::Literal(s) => Some(s.as_str()),
+            WordPart::Variable(_) => None,
+        }).collect()
+    }
+
+    pub fn variables(&self) -> impl Iterator<Item = &PSym> {
+        self.parts.iter().filter_map(|p| match p {
+            WordPart::Variable(v) => Some(v),
+            WordPart::Literal(_) => None,
+        })
+    }
+
+    /// Replaces each variable with `lookup` of its name, or nothing when `lookup` gives
+    /// `None`.  The result is not split again on whitespace.
+    pub fn expand<F : Fn(&str) -> Option<String>>(&self, lookup : F) -> String {
+        self.parts.iter().map(|p| match p {
+            WordPart::Literal(s) => s.clone(),
+            WordPart::Variable(v) => lookup(&v.value).unwrap_or_default(),
+        }).collect()
+    }
+}
+
+impl<'a> Input<'a> {
+
+    /// Splits the input into words the way a POSIX shell does before running a command.
+    /// Words are separated by whitespace, `'` quotes text literally, `"` quotes text
+    /// while still allowing variables and escaping `$`, `` ` ``, `"`, `\` and newlines
+    /// with `\`, and a `#` at the start of a word comments out the rest of the line.
+    /// Command substitution with `` ` `` or `$(` is an error.
+    pub fn parse_shell_words(&mut self) -> Result<Vec<ShellWord>, ParseError> {
+        let mut words = vec![];
+        loop {
+            skip_blanks(self);
+            match self.data {
+                [] => return Ok(words),
+                [(_, '#'), ..] => {
+                    while let [(_, c), rest @ ..] = self.data {
+                        if *c == '\n' {
+                            break;
+                        }
+                        self.data = rest;
+                    }
+                },
+                _ => words.push(word(self)?),
+            }
+        }
+    }
+}
+
+// whitespace and escaped newlines between words
+fn skip_blanks(input : &mut Input) {
+    loop {
+        match input.data {
+            [(_, c), rest @ ..] if c.is_whitespace() => input.data = rest,
+            [(_, '\\'), (_, '\n'), rest @ ..] => input.data = rest,
+            _ => return,
+        }
+    }
+}
+
+fn push_char(parts : &mut Vec<WordPart>, c : char) {
+    match parts.last_mut() {
+        Some(WordPart::Literal(s)) => s.push(c),
+        _ => parts.push(WordPart::Literal(c.to_string())),
+    }
+}
+
+fn word(input : &mut Input) -> Result<ShellWord, ParseError> {
+    let start = input.position();
+    let mut end = start;
+    let mut parts = vec![];
+
+    loop {
+        match input.data {
+            [] => break,
+            [(_, c), ..] if c.is_whitespace() => break,
+            [(_, '\\'), (_, '\n'), rest @ ..] => input.data = rest,
+            [(i, '\\')] => return Err(ParseError::EndOfFile(format!("Expected character after \\ at {}", i))),
+            [(_, '\\'), (i, c), rest @ ..] => {
+                input.data = rest;
+                end = *i;
+                push_char(&mut parts, *c);
+            },
+            [(_, '\''), rest @ ..] => {
+                input.data = rest;
+                // an empty quoted word is still a word
+                if parts.is_empty() {
+                    parts.push(WordPart::Literal(String::new()));
+                }
+                loop {
+                    match input.data {
+                        [] => return Err(input.unexpected("`'`")),
+                        [(i, '\''), rest @ ..] => {
+                            input.data = rest;
+                            end = *i;
+                            break;
+                        },
+                        [(_, c), rest @ ..] => {
+                            input.data = rest;
+                            push_char(&mut parts, *c);
+                        },
+                    }
+                }
+            },
+            [(_, '"'), rest @ ..] => {
+                input.data = rest;
+                if parts.is_empty() {
+                    parts.push(WordPart::Literal(String::new()));
+                }
+                end = double_quoted(input, &mut parts)?;
+            },
+            [(_, '$'), ..] => end = dollar(input, &mut parts)?,
+            [(i, '`'), ..] => return Err(substitution(*i)),
+            [(i, c), rest @ ..] => {
+                input.data = rest;
+                end = *i;
+                push_char(&mut parts, *c);
+            },
+        }
+    }
+
+    Ok(ShellWord { start, end, parts })
+}
+
+fn substitution(i : usize) -> ParseError {
+    ParseError::ErrorAt(i, "Encountered command substitution, which is not supported".to_string())
+}
+
+// returns the index of the closing `"`
+fn double_quoted(input : &mut Input, parts : &mut Vec<WordPart>) -> Result<usize, ParseError> {
+    loop {
+        match input.data {
+            [] => return Err(input.unexpected("`\"`")),
+            [(i, '"'), rest @ ..] => {
+                input.data = rest;
+                return Ok(*i);
+            },
+            [(_, '\\'), (_, '\n'), rest @ ..] => input.data = rest,
+            [(_, '\\'), (_, c @ ('$' | '`' | '"' | '\\')), rest @ ..] => {
+                input.data = rest;
+                push_char(parts, *c);
+            },
+            [(_, '$'), ..] => { dollar(input, parts)?; },
+            [(i, '`'), ..] => return Err(substitution(*i)),
+            [(_, c), rest @ ..] => {
+                input.data = rest;
+                push_char(parts, *c);
+            },
+        }
+    }
+}
+
+fn is_name_start(c : char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_name(c : char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// a variable reference, or a literal `$` when no name follows; returns the index of the
+// last character read
+fn dollar(input : &mut Input, parts : &mut Vec<WordPart>) -> Result<usize, ParseError> {
+    let start = input.position();
+    match input.data {
+        [_, (_, '('), ..] => Err(substitution(start)),
+        [_, (_, '{'), rest @ ..] => {
+            input.data = rest;
+            let name = match input.data {
+                [(_, c), ..] if is_name_start(*c) => input.data.iter().take_while(|(_, c)| is_name(*c)).count(),
+                _ => return Err(input.unexpected("variable name")),
+            };
+            let value = input.data[.. name].iter().map(|(_, c)| c).collect();
+            input.data = &input.data[name ..];
+            match input.data {
+                [(end, '}'), rest @ ..] => {
+                    input.data = rest;
+                    parts.push(WordPart::Variable(PSym { start, end: *end, value }));
+                    Ok(*end)
+                },
+                _ => Err(input.unexpected("`}`")),
+            }
+        },
+        [_, (_, c), ..] if is_name_start(*c) => {
+            let name = input.data[1 ..].iter().take_while(|(_, c)| is_name(*c)).count();
+            let end = input.data[name].0;
+            let value = input.data[1 ..= name].iter().map(|(_, c)| c).collect();
+            input.data = &input.data[name + 1 ..];
+            parts.push(WordPart::Variable(PSym { start, end, value }));
+            Ok(end)
+        },
+        _ => {
+            input.data = &input.data[1 ..];
+            push_char(parts, '$');
+            Ok(start)
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn split(s : &str) -> Result<Vec<ShellWord>, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        input.parse_shell_words()
+    }
+
+    fn literals(words : &[ShellWord]) -> Vec<String> {
+        words.iter().map(|w| w.literal().unwrap()).collect()
+    }
+
+    #[test]
+    fn should_split_words() -> Result<(), ParseError> {
+        let words = split(r#"  cp -r 'my dir'/ "a \"b\" \n" \$5 it\'s '' x\
+y # comment
+next"#)?;
+        assert_eq!( literals(&words), vec!["cp", "-r", "my dir/", "a \"b\" \\n", "$5", "it's", "", "xy", "next"] );
+        Ok(())
+    }
+
+    #[test]
+    fn should_find_variables() -> Result<(), ParseError> {
+        let words = split(r#"echo $HOME/bin "${USER}_x $1" '$NOT' a$"#)?;
+        let names = words.iter().flat_map(|w| w.variables().map(|v| (v.value.clone(), v.start, v.end))).collect::<Vec<_>>();
+        assert_eq!( names, vec![("HOME".to_string(), 5, 9), ("USER".to_string(), 16, 22)] );
+
+        let lookup = |name : &str| if name == "HOME" { Some("/home/me".to_string()) } else { None };
+        let expanded = words.iter().map(|w| w.expand(lookup)).collect::<Vec<_>>();
+        assert_eq!( expanded, vec!["echo", "/home/me/bin", "_x $1", "$NOT", "a$"] );
+        Ok(())
+    }
+
+    #[test]
+    fn should_give_spans() -> Result<(), ParseError> {
+        let words = split("ls  'a b'c \"d\"")?;
+        let spans = words.iter().map(|w| (w.start, w.end)).collect::<Vec<_>>();
+        assert_eq!( spans, vec![(0, 1), (4, 9), (11, 13)] );
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_bad_words() {
+        assert!( matches!( split("echo 'open"), Err(ParseError::EndOfFile(_)) ) );
+        assert!( matches!( split("echo \"open"), Err(ParseError::EndOfFile(_)) ) );
+        assert!( matches!( split("echo \\"), Err(ParseError::EndOfFile(_)) ) );
+        assert!( matches!( split("echo ${A"), Err(ParseError::EndOfFile(_)) ) );
+        assert!( matches!( split("echo ${1}"), Err(ParseError::ErrorAt(7, _)) ) );
+        assert!( matches!( split("echo $(ls)"), Err(ParseError::ErrorAt(5, _)) ) );
+        assert!( matches!( split("echo \"`ls`\""), Err(ParseError::ErrorAt(6, _)) ) );
+    }
+}
@@ -0,0 +1,463 @@
+use super::{DEFAULT_MAX_DEPTH, Input, ParseError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Symbol,
+    Keyword,
+    Number,
+    String,
+    Punctuation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind : TokenKind,
+    pub start : usize,
+    pub end : usize,
+    pub value : String,
+}
+
+/// Turns source into tokens with the `Input` primitives.  Symbols that appear in the
+/// keyword list become `Keyword` tokens.  Punctuation is tried before numbers, so `-1`
+/// is only a negative number when `-` isn't punctuation.
+#[derive(Debug, Clone, Default)]
+pub struct Lexer<'k> {
+    keywords : Vec<&'k str>,
+    punctuation : Vec<&'k str>,
+}
+
+impl<'k> Lexer<'k> {
+    pub fn new() -> Lexer<'k> {
+        Lexer { keywords: vec![], punctuation: vec![] }
+    }
+
+    pub fn keywords(mut self, keywords : &[&'k str]) -> Lexer<'k> {
+        self.keywords.extend_from_slice(keywords);
+        self
+    }
+
+    pub fn punctuation(mut self, punctuation : &[&'k str]) -> Lexer<'k> {
+        self.punctuation.extend_from_slice(punctuation);
+        // longest first so that `->` is found before `-`
+        self.punctuation.sort_by_key(|p| std::cmp::Reverse(p.len()));
+        self
+    }
+
+    pub fn tokenize(&self, input : &mut Input) -> Result<Vec<Token>, ParseError> {
+        let mut tokens = vec![];
+
+        while !input.at_end()? {
+            tokens.push(self.next_token(input)?);
+        }
+
+        Ok(tokens)
+    }
+
+    fn next_token(&self, input : &mut Input) -> Result<Token, ParseError> {
+        input.clear()?;
+
+        match input.data {
+            [(_, c), ..] if c.is_alphabetic() || *c == '_' => {
+                let sym = input.parse_symbol()?;
+                let kind = if self.keywords.contains(&sym.value.as_str()) { TokenKind::Keyword } else { TokenKind::Symbol };
+                return Ok(Token { kind, start: sym.start, end: sym.end, value: sym.value });
+            },
+            [(_, '"'), ..] => {
+                let s = input.parse_string()?;
+                return Ok(Token { kind: TokenKind::String, start: s.start, end: s.end, value: s.value });
+            },
+            _ => { },
+        }
+
+        for p in self.punctuation.iter() {
+            let rp = input.create_restore();
+            let start = input.position();
+            if input.raw_expect(p).is_ok() {
+                let end = start + p.len() - p.chars().last().map_or(1, |c| c.len_utf8());
+                return Ok(Token { kind: TokenKind::Punctuation, start, end, value: p.to_string() });
+            }
+            input.restore(rp);
+        }
+
+        match input.data {
+            [(_, c), ..] if c.is_ascii_digit() || *c == '-' => {
+                let n = input.parse_number()?;
+                Ok(Token { kind: TokenKind::Number, start: n.start, end: n.end, value: n.value })
+            },
+            [(i, c), ..] => Err(ParseError::ErrorAt(*i, format!("Encountered {} in tokenize", c))),
+            [] => Err(ParseError::EndOfFile("tokenize".to_string())),
+        }
+    }
+}
+
+pub struct TokenInput<'a> {
+    data : &'a [Token],
+    source : &'a [Token],
+    depth : usize,
+    max_depth : usize,
+    fuel : Option<usize>,
+}
+
+#[derive(Clone, Copy)]
+pub struct TokenRestorePoint<'a> {
+    data : &'a [Token],
+}
+
+impl<'a> TokenInput<'a> {
+
+    pub fn new(tokens : &'a [Token]) -> TokenInput<'a> {
+        TokenInput { data: tokens, source: tokens, depth: 0, max_depth: DEFAULT_MAX_DEPTH, fuel: None }
+    }
+
+    /// Limits how deeply combinators may nest before failing with `DepthExceeded`.  The
+    /// default is `DEFAULT_MAX_DEPTH`.
+    pub fn with_max_depth(mut self, max_depth : usize) -> TokenInput<'a> {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Limits how many parsers combinators may run before failing with `FuelExhausted`.
+    pub fn with_fuel(mut self, fuel : usize) -> TokenInput<'a> {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    pub fn create_restore(&self) -> TokenRestorePoint<'a> {
+        TokenRestorePoint { data: self.data }
+    }
+
+    pub fn restore(&mut self, restore_point : TokenRestorePoint<'a>) {
+        self.data = restore_point.data
+    }
+
+    pub fn remaining(&self) -> &'a [Token] {
+        self.data
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn expect_end(&mut self) -> Result<(), ParseError> {
+        match self.data {
+            [] => Ok(()),
+            [t, ..] => Err(ParseError::ErrorAt(t.start, format!("Expected end of input, but found {}", t.value))),
+        }
+    }
+
+    pub fn peek_token(&self) -> Option<&'a Token> {
+        self.data.first()
+    }
+
+    pub fn next_token(&mut self) -> Result<Token, ParseError> {
+        match self.data {
+            [] => Err(ParseError::EndOfFile("next_token".to_string())),
+            [t, rest @ ..] => {
+                self.data = rest;
+                Ok(t.clone())
+            },
+        }
+    }
+
+    pub fn expect_kind(&mut self, kind : TokenKind) -> Result<Token, ParseError> {
+        match self.data {
+            [] => Err(ParseError::EndOfFile(format!("Expected {:?} but found end of file", kind))),
+            [t, rest @ ..] if t.kind == kind => {
+                self.data = rest;
+                Ok(t.clone())
+            },
+            [t, ..] => Err(ParseError::ErrorAt(t.start, format!("Expected {:?} but found {}", kind, t.value))),
+        }
+    }
+
+    /// Expects a keyword or punctuation token with the given text.
+    pub fn expect(&mut self, s : &str) -> Result<(), ParseError> {
+        match self.data {
+            [] => Err(ParseError::EndOfFile(format!("Expected {} but found end of file", s))),
+            [t, rest @ ..] if (t.kind == TokenKind::Keyword || t.kind == TokenKind::Punctuation) && t.value == s => {
+                self.data = rest;
+                Ok(())
+            },
+            [t, ..] => Err(ParseError::ErrorAt(t.start, format!("Expected {} but found {}", s, t.value))),
+        }
+    }
+
+    pub fn parse_symbol(&mut self) -> Result<Token, ParseError> {
+        self.expect_kind(TokenKind::Symbol)
+    }
+
+    pub fn parse_number(&mut self) -> Result<Token, ParseError> {
+        self.expect_kind(TokenKind::Number)
+    }
+
+    pub fn parse_string(&mut self) -> Result<Token, ParseError> {
+        self.expect_kind(TokenKind::String)
+    }
+
+    pub fn cut<T>(&mut self, parse : fn(&mut TokenInput) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        match self.apply(parse) {
+            Err(e) if !e.is_fatal() => Err(ParseError::Fatal(Box::new(e))),
+            result => result,
+        }
+    }
+
+    pub fn context<T>(&mut self, label : &str, parse : fn(&mut TokenInput) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        self.apply(parse).map_err(|e| ParseError::Context(label.to_string(), Box::new(e)))
+    }
+
+    pub fn peek<T>(&mut self, parse : fn(&mut TokenInput) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        let rp = self.create_restore();
+        let result = self.apply(parse);
+        self.restore(rp);
+        result
+    }
+
+    pub fn not_followed_by<T>(&mut self, parse : fn(&mut TokenInput) -> Result<T, ParseError>) -> Result<(), ParseError> {
+        let rp = self.create_restore();
+        let result = self.apply(parse);
+        self.restore(rp);
+        match (result, self.data) {
+            (Ok(_), [t, ..]) => Err(ParseError::ErrorAt(t.start, "Encountered input rejected by not_followed_by".to_string())),
+            (Ok(_), []) => Err(ParseError::EndOfFile("Encountered input rejected by not_followed_by".to_string())),
+            (Err(e), _) if e.is_fatal() => Err(e),
+            (Err(_), _) => Ok(()),
+        }
+    }
+
+    pub fn maybe<T>(&mut self, parse : fn(&mut TokenInput) -> Result<T, ParseError>) -> Result<Option<T>, ParseError> {
+        let rp = self.create_restore();
+        match self.apply(parse) {
+            Ok(v) => Ok(Some(v)),
+            Err(e) if e.is_fatal() => Err(e),
+            Err(_) => {
+                self.restore(rp);
+                Ok(None)
+            },
+        }
+    }
+
+    pub fn zero_or_more<T>(&mut self, parse : fn(&mut TokenInput) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
+        let mut items = vec![];
+
+        loop {
+            let rp = self.create_restore();
+            match self.apply(parse) {
+                Ok(_) if self.data.len() == rp.data.len() => return Err(ParseError::NoProgress(self.position())),
+                Ok(v) => items.push(v),
+                Err(e) if e.is_fatal() => return Err(e),
+                Err(_) => {
+                    self.restore(rp);
+                    break
+                },
+            }
+        }
+
+        Ok(items)
+    }
+
+    pub fn one_or_more<T>(&mut self, parse : fn(&mut TokenInput) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
+        let mut items = vec![self.apply(parse)?];
+        items.append(&mut self.zero_or_more(parse)?);
+        Ok(items)
+    }
+
+    pub fn list<T>(&mut self, parse : fn(&mut TokenInput) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
+        self.sep_by(",", parse)
+    }
+
+    pub fn sep_by<T>(&mut self, sep : &str, parse : fn(&mut TokenInput) -> Result<T, ParseError>) -> Result<Vec<T>, ParseError> {
+        let mut items = vec![];
+
+        let rp = self.create_restore();
+        match self.apply(parse) {
+            Ok(item) => items.push(item),
+            Err(e) if e.is_fatal() => return Err(e),
+            Err(_) => {
+                self.restore(rp);
+                return Ok(vec![]);
+            },
+        }
+
+        while self.expect(sep).is_ok() {
+            items.push(self.apply(parse)?);
+        }
+
+        Ok(items)
+    }
+
+    #[allow(clippy::type_complexity, clippy::len_zero)]
+    pub fn choice<T>(&mut self, parsers : &[fn(&mut TokenInput) -> Result<T, ParseError>]) -> Result<T, ParseError> {
+
+        assert!( parsers.len() > 0, "choice must have at least one parser" );
+
+        let mut e = None;
+        for parse in parsers.iter().copied() {
+            let rp = self.create_restore();
+            match self.apply(parse) {
+                Ok(item) => return Ok(item),
+                Err(err) if err.is_fatal() => return Err(err),
+                Err(err) => {
+                    e = Some(err);
+                    self.restore(rp);
+                },
+            }
+        }
+
+        Err(e.expect("Encountered choice with zero successes and zero failures"))
+    }
+
+    fn apply<T>(&mut self, parse : fn(&mut TokenInput) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        self.enter()?;
+        let result = parse(self);
+        self.leave();
+        result
+    }
+
+    fn enter(&mut self) -> Result<(), ParseError> {
+        if self.depth >= self.max_depth {
+            return Err(ParseError::DepthExceeded(self.position()));
+        }
+        match self.fuel {
+            Some(0) => return Err(ParseError::FuelExhausted(self.position())),
+            Some(ref mut fuel) => *fuel -= 1,
+            None => (),
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    // past the end of the last token, like `Input` at the end of its characters
+    fn position(&self) -> usize {
+        match (self.data, self.source) {
+            ([t, ..], _) => t.start,
+            // strings end with `"` while the other kinds end with the last character of their value
+            ([], [.., t]) if t.kind == TokenKind::String => t.end + 1,
+            ([], [.., t]) => t.end + t.value.chars().last().map_or(1, char::len_utf8),
+            ([], []) => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lexer() -> Lexer<'static> {
+        Lexer::new().keywords(&["let", "in"])
+                    .punctuation(&["=", "==", "(", ")", ",", "+"])
+    }
+
+    fn tokens(s : &str) -> Result<Vec<Token>, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        lexer().tokenize(&mut input)
+    }
+
+    #[test]
+    fn should_tokenize() -> Result<(), ParseError> {
+        let ts = tokens("let x == /* c */ f(1.5, \"s\")+in")?;
+        let kinds = ts.iter().map(|t| t.kind).collect::<Vec<_>>();
+        assert_eq!( kinds, vec![ TokenKind::Keyword, TokenKind::Symbol, TokenKind::Punctuation, TokenKind::Symbol
+                               , TokenKind::Punctuation, TokenKind::Number, TokenKind::Punctuation, TokenKind::String
+                               , TokenKind::Punctuation, TokenKind::Punctuation, TokenKind::Keyword
+                               ] );
+        assert_eq!( ts[2], Token { kind: TokenKind::Punctuation, start: 6, end: 7, value: "==".to_string() } );
+        assert_eq!( ts[5].value, "1.5" );
+        assert_eq!( ts[5].start, 19 );
+        assert_eq!( ts[7].value, "s" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_tokenize_on_unknown_character() {
+        let e = tokens("x ? y");
+        assert!( matches!( e, Err(ParseError::ErrorAt(2, _)) ) );
+    }
+
+    fn binding(input : &mut TokenInput) -> Result<(String, Vec<String>), ParseError> {
+        input.expect("let")?;
+        let name = input.parse_symbol()?;
+        input.expect("=")?;
+        let args = input.choice(&[ |i| {
+                                         i.parse_symbol()?;
+                                         i.expect("(")?;
+                                         let args = i.list(|i| Ok(i.parse_number()?.value))?;
+                                         i.expect(")")?;
+                                         Ok(args)
+                                       }
+                                 , |i| Ok(vec![i.parse_number()?.value])
+                                 ])?;
+        Ok((name.value, args))
+    }
+
+    #[test]
+    fn should_parse_over_tokens() -> Result<(), ParseError> {
+        let ts = tokens("let x = f(1, 2) let y = 3")?;
+        let mut input = TokenInput::new(&ts);
+        let bindings = input.zero_or_more(binding)?;
+        input.expect_end()?;
+        assert_eq!( bindings.len(), 2 );
+        assert_eq!( bindings[0], ("x".to_string(), vec!["1".to_string(), "2".to_string()]) );
+        assert_eq!( bindings[1], ("y".to_string(), vec!["3".to_string()]) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_restore_over_tokens() -> Result<(), ParseError> {
+        let ts = tokens("let x = y")?;
+        let mut input = TokenInput::new(&ts);
+        let rp = input.create_restore();
+        assert!( input.maybe(binding)?.is_none() );
+        input.expect("let")?;
+        input.restore(rp);
+        assert_eq!( input.remaining().len(), 4 );
+        assert!( input.peek(|i| i.expect("let")).is_ok() );
+        assert!( input.not_followed_by(|i| i.expect("let")).is_err() );
+        Ok(())
+    }
+
+    fn nested(input : &mut TokenInput) -> Result<usize, ParseError> {
+        input.expect("(")?;
+        let depth = input.maybe(nested)?.map_or(0, |d| d + 1);
+        input.expect(")")?;
+        Ok(depth)
+    }
+
+    #[test]
+    fn should_limit_depth_and_fuel_over_tokens() -> Result<(), ParseError> {
+        let ts = tokens(&format!("{}{}", "(".repeat(10), ")".repeat(10)))?;
+        assert_eq!( TokenInput::new(&ts).maybe(nested)?, Some(9) );
+        assert!( matches!( TokenInput::new(&ts).with_max_depth(5).maybe(nested), Err(ParseError::DepthExceeded(5)) ) );
+        assert!( matches!( TokenInput::new(&ts).with_fuel(3).maybe(nested), Err(ParseError::FuelExhausted(3)) ) );
+
+        let ts = tokens(&"(".repeat(100_000))?;
+        assert!( matches!( TokenInput::new(&ts).maybe(nested), Err(ParseError::DepthExceeded(_)) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_position_at_end_of_tokens() -> Result<(), ParseError> {
+        let data = "let x = 3 λ".char_indices().collect::<Vec<(usize, char)>>();
+        let mut source = Input::new(&data);
+        let ts = lexer().tokenize(&mut source)?;
+        let e = source.zero_or_more(|i| i.maybe(|i| i.expect("x"))).unwrap_err();
+        assert!( matches!( e, ParseError::NoProgress(12) ) );
+
+        let mut input = TokenInput::new(&ts);
+        input.zero_or_more(binding)?;
+        input.parse_symbol()?;
+        let e = input.zero_or_more(|i| i.maybe(binding)).unwrap_err();
+        assert!( matches!( e, ParseError::NoProgress(12) ) );
+
+        let ts = tokens("\"s\"")?;
+        let mut input = TokenInput::new(&ts);
+        input.parse_string()?;
+        let e = input.zero_or_more(|i| i.maybe(binding)).unwrap_err();
+        assert!( matches!( e, ParseError::NoProgress(3) ) );
+        Ok(())
+    }
+}
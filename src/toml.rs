@@ -0,0 +1,821 @@
+use super::{Input, PSym, ParseError, StringFormat, StringSpec, SymbolSpec, Whitespace};
+
+/// A date, time or both, with an offset only when both are given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TomlDatetime {
+    /// Year, month and day.
+    pub date : Option<(u16, u8, u8)>,
+    /// Hour, minute, second and nanosecond.
+    pub time : Option<(u8, u8, u8, u32)>,
+    /// Minutes east of UTC.
+    pub offset : Option<i16>,
+}
+
+impl std::fmt::Display for TomlDatetime {
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some((year, month, day)) = self.date {
+            write!(f, "{:04}-{:02}-{:02}", year, month, day)?;
+            if self.time.is_some() {
+                write!(f, "T")?;
+            }
+        }
+        if let Some((hour, minute, second, nanosecond)) = self.time {
+            write!(f, "{:02}:{:02}:{:02}", hour, minute, second)?;
+            if nanosecond > 0 {
+                write!(f, ".{}", format!("{:09}", nanosecond).trim_end_matches('0'))?;
+            }
+        }
+        match self.offset {
+            Some(0) => write!(f, "Z"),
+            Some(offset) => write!(f, "{}{:02}:{:02}", if offset < 0 { '-' } else { '+' }, offset.abs() / 60, offset.abs() % 60),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TomlValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Datetime(TomlDatetime),
+    /// Arrays, including arrays of tables.
+    Array(Vec<Toml>),
+    /// Keys in the order they were first defined.
+    Table(Vec<(PSym, Toml)>),
+}
+
+/// A TOML value with the byte indices of its first and last characters.  Tables span the
+/// header or key that first defined them, and the document spans all of the input.
+#[derive(Debug, Clone)]
+pub struct Toml {
+    pub start : usize,
+    pub end : usize,
+    pub value : TomlValue,
+}
+
+impl Toml {
+    pub fn get(&self, key : &str) -> Option<&Toml> {
+        match &self.value {
+            TomlValue::Table(entries) => entries.iter().find(|(k, _)| k.value == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            TomlValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match &self.value {
+            TomlValue::Integer(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Toml]> {
+        match &self.value {
+            TomlValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Input<'a> {
+
+    /// Parses a TOML 1.0 document until the end of input.  Tables defined by headers,
+    /// dotted keys and `[[array]]` headers are merged into one tree, and redefining a key
+    /// or table is an error at the later key.
+    pub fn parse_toml(&mut self) -> Result<Toml, ParseError> {
+        let whitespace = self.whitespace;
+        self.whitespace = Whitespace::Blank;
+        let result = document(self);
+        self.whitespace = whitespace;
+        result
+    }
+}
+
+fn document(input : &mut Input) -> Result<Toml, ParseError> {
+    let end = input.source.last().map_or(0, |(i, _)| *i);
+    let mut root = Table { start: 0, end, kind: Kind::Header, entries: vec![] };
+    let mut current : Vec<PSym> = vec![];
+
+    loop {
+        skip_whitespace(input);
+        match input.data {
+            [] => break,
+            [(_, '#'), ..] => { },
+            [(_, '\n'), ..] | [(_, '\r'), (_, '\n'), ..] => { },
+            [(_, '['), (_, '['), ..] => {
+                let (keys, start, end) = header(input, "[[", "]]")?;
+                array_header(&mut root, &keys, start, end)?;
+                current = keys;
+            },
+            [(_, '['), ..] => {
+                let (keys, start, end) = header(input, "[", "]")?;
+                table_header(&mut root, &keys, start, end)?;
+                current = keys;
+            },
+            _ => {
+                let (keys, value) = key_value(input)?;
+                insert(resolve(&mut root, &current), &keys, value)?;
+            },
+        }
+        end_of_line(input)?;
+    }
+
+    Ok(root.into_toml())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    // created on the way to a header's table
+    Implicit,
+    Header,
+    Dotted,
+}
+
+enum Item {
+    Value(Toml),
+    Table(Table),
+    Tables(Vec<Table>),
+}
+
+struct Table {
+    start : usize,
+    end : usize,
+    kind : Kind,
+    entries : Vec<(PSym, Item)>,
+}
+
+impl Table {
+    fn new(key : &PSym, kind : Kind) -> Table {
+        Table { start: key.start, end: key.end, kind, entries: vec![] }
+    }
+
+    fn find(&mut self, key : &str) -> Option<&mut Item> {
+        self.entries.iter_mut().find(|(k, _)| k.value == key).map(|(_, item)| item)
+    }
+
+    // adds an item that isn't already there and returns it
+    fn add(&mut self, key : &PSym, item : Item) -> &mut Item {
+        self.entries.push((key.clone(), item));
+        &mut self.entries.last_mut().unwrap().1
+    }
+
+    fn into_toml(self) -> Toml {
+        let entries = self.entries.into_iter().map(|(k, item)| (k, item.into_toml())).collect();
+        Toml { start: self.start, end: self.end, value: TomlValue::Table(entries) }
+    }
+}
+
+impl Item {
+    fn into_toml(self) -> Toml {
+        match self {
+            Item::Value(v) => v,
+            Item::Table(t) => t.into_toml(),
+            Item::Tables(ts) => {
+                let (start, end) = (ts[0].start, ts[ts.len() - 1].end);
+                Toml { start, end, value: TomlValue::Array(ts.into_iter().map(Table::into_toml).collect()) }
+            },
+        }
+    }
+}
+
+fn redefined(key : &PSym) -> ParseError {
+    ParseError::ErrorAt(key.start, format!("Key {} is already defined", key.value))
+}
+
+// the table that key values after the header at `path` go into
+fn resolve<'t>(root : &'t mut Table, path : &[PSym]) -> &'t mut Table {
+    let mut table = root;
+    for key in path {
+        table = match table.find(&key.value) {
+            Some(Item::Table(t)) => t,
+            Some(Item::Tables(ts)) => ts.last_mut().unwrap(),
+            _ => unreachable!("headers are checked when they are parsed"),
+        };
+    }
+    table
+}
+
+// walks to the table that will hold the last key of a header, creating implicit tables
+fn header_parent<'t>(root : &'t mut Table, keys : &[PSym]) -> Result<&'t mut Table, ParseError> {
+    let mut table = root;
+    for key in &keys[.. keys.len() - 1] {
+        if table.find(&key.value).is_none() {
+            table.add(key, Item::Table(Table::new(key, Kind::Implicit)));
+        }
+        table = match table.find(&key.value) {
+            Some(Item::Table(t)) => t,
+            Some(Item::Tables(ts)) => ts.last_mut().unwrap(),
+            _ => return Err(redefined(key)),
+        };
+    }
+    Ok(table)
+}
+
+fn table_header(root : &mut Table, keys : &[PSym], start : usize, end : usize) -> Result<(), ParseError> {
+    let last = keys.last().unwrap();
+    let parent = header_parent(root, keys)?;
+    match parent.find(&last.value) {
+        None => {
+            parent.add(last, Item::Table(Table { start, end, kind: Kind::Header, entries: vec![] }));
+            Ok(())
+        },
+        Some(Item::Table(t)) if t.kind == Kind::Implicit => {
+            t.kind = Kind::Header;
+            t.start = start;
+            t.end = end;
+            Ok(())
+        },
+        Some(_) => Err(redefined(last)),
+    }
+}
+
+fn array_header(root : &mut Table, keys : &[PSym], start : usize, end : usize) -> Result<(), ParseError> {
+    let last = keys.last().unwrap();
+    let parent = header_parent(root, keys)?;
+    let table = Table { start, end, kind: Kind::Header, entries: vec![] };
+    match parent.find(&last.value) {
+        None => {
+            parent.add(last, Item::Tables(vec![table]));
+            Ok(())
+        },
+        Some(Item::Tables(ts)) => {
+            ts.push(table);
+            Ok(())
+        },
+        Some(_) => Err(redefined(last)),
+    }
+}
+
+// dotted keys may only extend tables that were themselves made by dotted keys
+fn insert(table : &mut Table, keys : &[PSym], value : Toml) -> Result<(), ParseError> {
+    let mut table = table;
+    for key in &keys[.. keys.len() - 1] {
+        if table.find(&key.value).is_none() {
+            table.add(key, Item::Table(Table::new(key, Kind::Dotted)));
+        }
+        table = match table.find(&key.value) {
+            Some(Item::Table(t)) if t.kind == Kind::Dotted => t,
+            _ => return Err(redefined(key)),
+        };
+    }
+
+    let last = keys.last().unwrap();
+    if table.find(&last.value).is_some() {
+        return Err(redefined(last));
+    }
+    table.add(last, Item::Value(value));
+    Ok(())
+}
+
+fn is_control(c : char) -> bool {
+    (c < '\u{20}' && c != '\t') || c == '\u{7f}'
+}
+
+fn skip_whitespace(input : &mut Input) {
+    while let [(_, ' ' | '\t'), rest @ ..] = input.data {
+        input.data = rest;
+    }
+}
+
+fn skip_comment(input : &mut Input) -> Result<(), ParseError> {
+    if let [(_, '#'), rest @ ..] = input.data {
+        input.data = rest;
+        while let [(i, c), rest @ ..] = input.data {
+            match c {
+                '\n' => break,
+                '\r' if matches!( rest, [(_, '\n'), ..] ) => break,
+                c if is_control(*c) => return Err(ParseError::ErrorAt(*i, format!("Encountered control character {:?} in comment", c))),
+                _ => input.data = rest,
+            }
+        }
+    }
+    Ok(())
+}
+
+fn end_of_line(input : &mut Input) -> Result<(), ParseError> {
+    skip_whitespace(input);
+    skip_comment(input)?;
+    match input.data {
+        [] => Ok(()),
+        [(_, '\n'), rest @ ..] | [(_, '\r'), (_, '\n'), rest @ ..] => {
+            input.data = rest;
+            Ok(())
+        },
+        _ => Err(input.unexpected("end of line")),
+    }
+}
+
+// whitespace, comments and newlines inside arrays
+fn skip_blank_lines(input : &mut Input) -> Result<(), ParseError> {
+    loop {
+        skip_whitespace(input);
+        skip_comment(input)?;
+        match input.data {
+            [(_, '\n'), rest @ ..] | [(_, '\r'), (_, '\n'), rest @ ..] => input.data = rest,
+            _ => return Ok(()),
+        }
+    }
+}
+
+fn expect(input : &mut Input, s : &str) -> Result<usize, ParseError> {
+    let start = input.position();
+    input.raw_expect(s)?;
+    Ok(start)
+}
+
+fn header(input : &mut Input, open : &str, close : &str) -> Result<(Vec<PSym>, usize, usize), ParseError> {
+    let start = expect(input, open)?;
+    skip_whitespace(input);
+    let keys = key(input)?;
+    skip_whitespace(input);
+    let end = expect(input, close)? + close.len() - 1;
+    Ok((keys, start, end))
+}
+
+fn key_value(input : &mut Input) -> Result<(Vec<PSym>, Toml), ParseError> {
+    let keys = key(input)?;
+    skip_whitespace(input);
+    expect(input, "=")?;
+    skip_whitespace(input);
+    Ok((keys, value(input)?))
+}
+
+fn key(input : &mut Input) -> Result<Vec<PSym>, ParseError> {
+    input.sep_by1(".", simple_key)
+}
+
+fn is_bare(c : char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+fn basic(input : &mut Input) -> Result<PSym, ParseError> {
+    input.parse_string_with(&StringSpec::new().format(StringFormat::Toml))
+}
+
+fn literal(input : &mut Input) -> Result<PSym, ParseError> {
+    input.parse_string_with(&StringSpec::new().quote('\'').format(StringFormat::TomlLiteral))
+}
+
+// bare keys may also start with a digit or `-`
+fn simple_key(input : &mut Input) -> Result<PSym, ParseError> {
+    skip_whitespace(input);
+    match input.data {
+        [(_, '"'), (_, '"'), (_, '"'), ..] | [(_, '\''), (_, '\''), (_, '\''), ..] =>
+            Err(ParseError::ErrorAt(input.position(), "Encountered multi-line string as a key".to_string())),
+        [(_, '"'), ..] => basic(input),
+        [(_, '\''), ..] => literal(input),
+        [(_, c), ..] if is_bare(*c) => input.parse_symbol_with(&SymbolSpec::new().start(is_bare).rest(is_bare)),
+        _ => Err(input.unexpected("key")),
+    }
+}
+
+fn value(input : &mut Input) -> Result<Toml, ParseError> {
+    let string = |s : PSym| Toml { start: s.start, end: s.end, value: TomlValue::String(s.value) };
+    match input.data {
+        [(_, '"'), (_, '"'), (_, '"'), ..] =>
+            Ok(string(input.parse_string_with(&StringSpec::new().format(StringFormat::Toml).multiline())?)),
+        [(_, '"'), ..] => Ok(string(basic(input)?)),
+        [(_, '\''), (_, '\''), (_, '\''), ..] =>
+            Ok(string(input.parse_string_with(&StringSpec::new().quote('\'').format(StringFormat::TomlLiteral).multiline())?)),
+        [(_, '\''), ..] => Ok(string(literal(input)?)),
+        [(_, 't'), ..] => {
+            let start = expect(input, "true")?;
+            Ok(Toml { start, end: start + 3, value: TomlValue::Boolean(true) })
+        },
+        [(_, 'f'), ..] => {
+            let start = expect(input, "false")?;
+            Ok(Toml { start, end: start + 4, value: TomlValue::Boolean(false) })
+        },
+        [(_, '['), ..] => nested(input, array),
+        [(_, '{'), ..] => nested(input, inline_table),
+        [(_, a), (_, b), (_, c), (_, d), (_, '-'), ..] if [a, b, c, d].iter().all(|c| c.is_ascii_digit()) => datetime(input),
+        [(_, a), (_, b), (_, ':'), ..] if a.is_ascii_digit() && b.is_ascii_digit() => datetime(input),
+        [(_, c), ..] if c.is_ascii_digit() || "+-in".contains(*c) => number(input),
+        _ => Err(input.unexpected("value")),
+    }
+}
+
+fn nested(input : &mut Input, parse : fn(&mut Input) -> Result<Toml, ParseError>) -> Result<Toml, ParseError> {
+    input.apply(parse)
+}
+
+fn array(input : &mut Input) -> Result<Toml, ParseError> {
+    let start = expect(input, "[")?;
+    let mut items = vec![];
+    loop {
+        skip_blank_lines(input)?;
+        if let [(end, ']'), rest @ ..] = input.data {
+            input.data = rest;
+            return Ok(Toml { start, end: *end, value: TomlValue::Array(items) });
+        }
+        items.push(value(input)?);
+        skip_blank_lines(input)?;
+        match input.data {
+            [(_, ','), rest @ ..] => input.data = rest,
+            [(_, ']'), ..] => { },
+            _ => return Err(input.unexpected("`,` or `]`")),
+        }
+    }
+}
+
+fn inline_table(input : &mut Input) -> Result<Toml, ParseError> {
+    let start = expect(input, "{")?;
+    let mut table = Table { start, end: start, kind: Kind::Dotted, entries: vec![] };
+    skip_whitespace(input);
+    if let [(end, '}'), rest @ ..] = input.data {
+        input.data = rest;
+        table.end = *end;
+        return Ok(table.into_toml());
+    }
+    loop {
+        skip_whitespace(input);
+        let (keys, value) = key_value(input)?;
+        insert(&mut table, &keys, value)?;
+        skip_whitespace(input);
+        match input.data {
+            [(_, ','), rest @ ..] => input.data = rest,
+            [(end, '}'), rest @ ..] => {
+                input.data = rest;
+                table.end = *end;
+                return Ok(table.into_toml());
+            },
+            _ => return Err(input.unexpected("`,` or `}`")),
+        }
+    }
+}
+
+// digits in `radix` where each underscore must be between two digits
+fn digits(input : &mut Input, radix : u32) -> Result<String, ParseError> {
+    let mut value = String::new();
+    match input.data {
+        [(_, c), rest @ ..] if c.is_digit(radix) => {
+            input.data = rest;
+            value.push(*c);
+        },
+        _ => return Err(input.unexpected("digit")),
+    }
+    loop {
+        match input.data {
+            [(_, c), rest @ ..] if c.is_digit(radix) => {
+                input.data = rest;
+                value.push(*c);
+            },
+            [(_, '_'), (_, c), rest @ ..] if c.is_digit(radix) => {
+                input.data = rest;
+                value.push(*c);
+            },
+            [(i, '_'), ..] => return Err(ParseError::ErrorAt(*i, "Expected digit after _".to_string())),
+            _ => return Ok(value),
+        }
+    }
+}
+
+fn number(input : &mut Input) -> Result<Toml, ParseError> {
+    let start = input.position();
+    let finish = |input : &Input, value| Toml { start, end: input.position() - 1, value };
+
+    let mut text = String::new();
+    if let [(_, sign @ ('+' | '-')), rest @ ..] = input.data {
+        input.data = rest;
+        text.push(*sign);
+    }
+
+    for (word, float) in [("inf", f64::INFINITY), ("nan", f64::NAN)].iter() {
+        if input.raw_expect(word).is_ok() {
+            let float = if text == "-" { -float } else { *float };
+            return Ok(finish(input, TomlValue::Float(float)));
+        }
+    }
+
+    if text.is_empty() {
+        let radix = match input.data {
+            [(_, '0'), (_, 'x'), ..] => 16,
+            [(_, '0'), (_, 'o'), ..] => 8,
+            [(_, '0'), (_, 'b'), ..] => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            input.data = &input.data[2 ..];
+            let digits = digits(input, radix)?;
+            return match i64::from_str_radix(&digits, radix) {
+                Ok(n) => Ok(finish(input, TomlValue::Integer(n))),
+                Err(_) => Err(ParseError::ErrorAt(start, "Encountered integer out of range".to_string())),
+            };
+        }
+    }
+
+    let int = digits(input, 10)?;
+    if int.len() > 1 && int.starts_with('0') {
+        return Err(ParseError::ErrorAt(start, "Encountered leading zero in number".to_string()));
+    }
+    text.push_str(&int);
+
+    let mut float = false;
+    if let [(_, '.'), rest @ ..] = input.data {
+        input.data = rest;
+        text.push('.');
+        text.push_str(&digits(input, 10)?);
+        float = true;
+    }
+    if let [(_, e @ ('e' | 'E')), rest @ ..] = input.data {
+        input.data = rest;
+        text.push(*e);
+        if let [(_, sign @ ('+' | '-')), rest @ ..] = input.data {
+            input.data = rest;
+            text.push(*sign);
+        }
+        text.push_str(&digits(input, 10)?);
+        float = true;
+    }
+
+    if float {
+        Ok(finish(input, TomlValue::Float(text.parse().unwrap())))
+    }
+    else {
+        match text.parse() {
+            Ok(n) => Ok(finish(input, TomlValue::Integer(n))),
+            Err(_) => Err(ParseError::ErrorAt(start, "Encountered integer out of range".to_string())),
+        }
+    }
+}
+
+fn fixed_digits(input : &mut Input, n : usize) -> Result<u32, ParseError> {
+    let mut value = 0;
+    for _ in 0 .. n {
+        match input.data {
+            [(_, c), rest @ ..] if c.is_ascii_digit() => {
+                input.data = rest;
+                value = value * 10 + c.to_digit(10).unwrap();
+            },
+            _ => return Err(input.unexpected("digit")),
+        }
+    }
+    Ok(value)
+}
+
+fn check(start : usize, ok : bool, what : &str) -> Result<(), ParseError> {
+    if ok {
+        Ok(())
+    }
+    else {
+        Err(ParseError::ErrorAt(start, format!("Encountered invalid {}", what)))
+    }
+}
+
+fn date(input : &mut Input) -> Result<(u16, u8, u8), ParseError> {
+    let start = input.position();
+    let year = fixed_digits(input, 4)?;
+    expect(input, "-")?;
+    let month = fixed_digits(input, 2)?;
+    expect(input, "-")?;
+    let day = fixed_digits(input, 2)?;
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    check(start, (1 ..= 12).contains(&month) && (1 ..= days).contains(&day), "date")?;
+    Ok((year as u16, month as u8, day as u8))
+}
+
+fn time(input : &mut Input) -> Result<(u8, u8, u8, u32), ParseError> {
+    let start = input.position();
+    let hour = fixed_digits(input, 2)?;
+    expect(input, ":")?;
+    let minute = fixed_digits(input, 2)?;
+    expect(input, ":")?;
+    let second = fixed_digits(input, 2)?;
+    check(start, hour < 24 && minute < 60 && second <= 60, "time")?;
+
+    let mut nanosecond = 0;
+    if let [(_, '.'), rest @ ..] = input.data {
+        input.data = rest;
+        let count = input.data.iter().take_while(|(_, c)| c.is_ascii_digit()).count();
+        if count == 0 {
+            return Err(input.unexpected("digit"));
+        }
+        // digits past nanoseconds are dropped
+        for (place, (_, c)) in input.data[.. count].iter().enumerate().take(9) {
+            nanosecond += c.to_digit(10).unwrap() * 10u32.pow(8 - place as u32);
+        }
+        input.data = &input.data[count ..];
+    }
+    Ok((hour as u8, minute as u8, second as u8, nanosecond))
+}
+
+fn datetime(input : &mut Input) -> Result<Toml, ParseError> {
+    let start = input.position();
+    let mut datetime = TomlDatetime { date: None, time: None, offset: None };
+
+    match input.data {
+        [_, _, (_, ':'), ..] => datetime.time = Some(time(input)?),
+        _ => {
+            datetime.date = Some(date(input)?);
+            match input.data {
+                [(_, 'T' | 't'), (_, d), ..] | [(_, ' '), (_, d), ..] if d.is_ascii_digit() => {
+                    input.data = &input.data[1 ..];
+                    datetime.time = Some(time(input)?);
+                },
+                _ => { },
+            }
+        },
+    }
+
+    if datetime.date.is_some() && datetime.time.is_some() {
+        match input.data {
+            [(_, 'Z' | 'z'), rest @ ..] => {
+                input.data = rest;
+                datetime.offset = Some(0);
+            },
+            [(_, sign @ ('+' | '-')), rest @ ..] => {
+                let sign = if *sign == '-' { -1 } else { 1 };
+                let offset_start = input.position();
+                input.data = rest;
+                let hours = fixed_digits(input, 2)?;
+                expect(input, ":")?;
+                let minutes = fixed_digits(input, 2)?;
+                check(offset_start, hours < 24 && minutes < 60, "offset")?;
+                datetime.offset = Some(sign * (hours * 60 + minutes) as i16);
+            },
+            _ => { },
+        }
+    }
+
+    Ok(Toml { start, end: input.position() - 1, value: TomlValue::Datetime(datetime) })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Json, JsonMode, JsonValue};
+
+    fn parse(s : &str) -> Result<Toml, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        input.parse_toml()
+    }
+
+    #[test]
+    fn should_parse_toml() -> Result<(), ParseError> {
+        let doc = parse(r#"
+# comment
+title = "TOML \u00e9xample"
+"quoted key" = 'C:\path'
+site."google.com" = true
+
+[owner]
+name = "Tom"
+dob = 1979-05-27T07:32:00-08:00
+
+[database]
+ports = [ 8000, 8001, 0x1F, 0o17, 0b11, 1_000 ]
+data = [ ["gamma", "delta"], [1.5, -2e3, inf] ]
+temp = { cpu = 79.5, case.inner = 72 }
+text = """
+Roses are red\
+   Violets are "blue" """
+
+[[products]]
+name = "Hammer"
+
+[[products]]
+[products.size]
+width = 3
+"#)?;
+        assert_eq!( doc.get("title").unwrap().as_str(), Some("TOML éxample") );
+        assert_eq!( doc.get("quoted key").unwrap().as_str(), Some("C:\\path") );
+        assert!( matches!( doc.get("site").unwrap().get("google.com").unwrap().value, TomlValue::Boolean(true) ) );
+
+        let dob = doc.get("owner").unwrap().get("dob").unwrap();
+        assert_eq!( to_string_value(dob), "1979-05-27T07:32:00-08:00" );
+
+        let database = doc.get("database").unwrap();
+        let ports = database.get("ports").unwrap().as_array().unwrap().iter().map(|p| p.as_integer().unwrap()).collect::<Vec<_>>();
+        assert_eq!( ports, vec![8000, 8001, 31, 15, 3, 1000] );
+        assert_eq!( database.get("temp").unwrap().get("case").unwrap().get("inner").unwrap().as_integer(), Some(72) );
+        assert_eq!( database.get("text").unwrap().as_str(), Some("Roses are redViolets are \"blue\" ") );
+
+        let products = doc.get("products").unwrap().as_array().unwrap();
+        assert_eq!( products.len(), 2 );
+        assert_eq!( products[1].get("size").unwrap().get("width").unwrap().as_integer(), Some(3) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_give_spans() -> Result<(), ParseError> {
+        let doc = parse("[a]\nkey = 'value'\n")?;
+        let a = doc.get("a").unwrap();
+        assert_eq!( (a.start, a.end), (0, 2) );
+        let value = a.get("key").unwrap();
+        assert_eq!( (value.start, value.end), (10, 16) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_redefinitions() {
+        assert!( matches!( parse("a = 1\na = 2"), Err(ParseError::ErrorAt(6, _)) ) );
+        assert!( matches!( parse("[a]\n[a]"), Err(ParseError::ErrorAt(5, _)) ) );
+        assert!( parse("[a]\nb.c = 1\n[a.b]").is_err() );
+        assert!( parse("a = {}\n[a]").is_err() );
+        assert!( parse("[[a]]\n[a]").is_err() );
+        assert!( parse("a = []\n[[a]]").is_err() );
+    }
+
+    fn to_string_value(toml : &Toml) -> String {
+        match &toml.value {
+            TomlValue::Datetime(d) => d.to_string(),
+            v => format!("{:?}", v),
+        }
+    }
+
+    fn datetime_text(s : &str) -> Result<TomlDatetime, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        match datetime(&mut input)?.value {
+            TomlValue::Datetime(d) => Ok(d),
+            _ => unreachable!(),
+        }
+    }
+
+    // compares against the tagged JSON used by toml-test
+    fn same(toml : &Toml, json : &Json) -> bool {
+        match (&toml.value, &json.value) {
+            (TomlValue::Array(items), JsonValue::Array(expected)) =>
+                items.len() == expected.len() && items.iter().zip(expected.iter()).all(|(t, j)| same(t, j)),
+            (_, JsonValue::Object(members)) if members.len() == 2 && json.get("type").is_some() && json.get("value").is_some() => {
+                let value = json.get("value").unwrap().as_str().unwrap();
+                match (json.get("type").unwrap().as_str().unwrap(), &toml.value) {
+                    ("string", TomlValue::String(s)) => s == value,
+                    ("integer", TomlValue::Integer(n)) => value.parse() == Ok(*n),
+                    ("float", TomlValue::Float(f)) if value == "nan" => f.is_nan(),
+                    ("float", TomlValue::Float(f)) => value.replace("inf", "infinity").parse() == Ok(*f),
+                    ("bool", TomlValue::Boolean(b)) => value == b.to_string(),
+                    ("datetime", TomlValue::Datetime(d)) => d.offset.is_some() && datetime_text(value).ok() == Some(*d),
+                    ("datetime-local", TomlValue::Datetime(d)) => d.offset.is_none() && datetime_text(value).ok() == Some(*d),
+                    ("date-local", TomlValue::Datetime(d)) => d.time.is_none() && datetime_text(value).ok() == Some(*d),
+                    ("time-local", TomlValue::Datetime(d)) => d.date.is_none() && datetime_text(value).ok() == Some(*d),
+                    _ => false,
+                }
+            },
+            (TomlValue::Table(entries), JsonValue::Object(members)) =>
+                entries.len() == members.len() && entries.iter().all(|(k, v)| json.get(&k.value).is_some_and(|j| same(v, j))),
+            _ => false,
+        }
+    }
+
+    // every `.toml` file below `dir`
+    fn collect(dir : &std::path::Path, paths : &mut Vec<std::path::PathBuf>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                collect(&path, paths);
+            }
+            else if path.extension().is_some_and(|e| e == "toml") {
+                paths.push(path);
+            }
+        }
+    }
+
+    fn fixtures(kind : &str) -> Vec<std::path::PathBuf> {
+        let dir = format!("{}/fixtures/toml/{}", env!("CARGO_MANIFEST_DIR"), kind);
+        let mut paths = vec![];
+        collect(dir.as_ref(), &mut paths);
+        paths.sort();
+        assert!( !paths.is_empty() );
+        paths
+    }
+
+    #[test]
+    fn should_pass_valid_toml_tests() {
+        for path in fixtures("valid") {
+            let text = std::fs::read_to_string(&path).unwrap();
+            let expected = std::fs::read_to_string(path.with_extension("json")).unwrap();
+            let data = expected.char_indices().collect::<Vec<(usize, char)>>();
+            let expected = Input::new(&data).parse_json_document(JsonMode::Strict).unwrap();
+
+            let result = parse(&text);
+            assert!( result.as_ref().is_ok_and(|t| same(t, &expected)), "{:?} gave {:?}", path, result );
+        }
+    }
+
+    #[test]
+    fn should_pass_invalid_toml_tests() {
+        for path in fixtures("invalid") {
+            // documents that aren't UTF-8 never reach the parser
+            if let Ok(text) = String::from_utf8(std::fs::read(&path).unwrap()) {
+                assert!( parse(&text).is_err(), "{:?} should be rejected", path );
+            }
+        }
+    }
+}
@@ -0,0 +1,502 @@
+use super::{Input, PSym, ParseError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HostKind {
+    RegName,
+    Ipv4([u8; 4]),
+    Ipv6([u16; 8]),
+    /// `[v1.x]`, kept as written.
+    IpvFuture,
+}
+
+/// The part after `//`.  IP literals keep their brackets in `host`.
+#[derive(Debug, Clone)]
+pub struct Authority {
+    pub userinfo : Option<PSym>,
+    pub host : PSym,
+    pub host_kind : HostKind,
+    /// The digits after `:`, which RFC 3986 allows to be empty.
+    pub port : Option<PSym>,
+}
+
+/// A URI reference split into its components as written, still percent-encoded.  Empty
+/// components have `start == end` at the position where they would have been.
+#[derive(Debug, Clone)]
+pub struct Uri {
+    pub scheme : Option<PSym>,
+    pub authority : Option<Authority>,
+    pub path : PSym,
+    pub query : Option<PSym>,
+    pub fragment : Option<PSym>,
+}
+
+/// Decodes `%HH` sequences, giving `None` when they aren't valid UTF-8.
+pub fn percent_decode(s : &str) -> Option<String> {
+    let mut bytes = vec![];
+    let mut rest = s.as_bytes();
+    while let [b, tail @ ..] = rest {
+        match (b, tail) {
+            (b'%', [h, l, tail @ ..]) if h.is_ascii_hexdigit() && l.is_ascii_hexdigit() => {
+                let hex = [*h, *l];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).unwrap(), 16).unwrap());
+                rest = tail;
+            },
+            _ => {
+                bytes.push(*b);
+                rest = tail;
+            },
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+impl Authority {
+    pub fn port_number(&self) -> Option<u16> {
+        self.port.as_ref()?.value.parse().ok()
+    }
+}
+
+impl std::fmt::Display for Authority {
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(userinfo) = &self.userinfo {
+            write!(f, "{}@", userinfo.value)?;
+        }
+        write!(f, "{}", self.host.value)?;
+        if let Some(port) = &self.port {
+            write!(f, ":{}", port.value)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Uri {
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+        let parts = Parts::of(self);
+        write!(f, "{}", parts.recompose())
+    }
+}
+
+impl Uri {
+    /// Resolves `reference` against this URI as the base, following section 5.2 of
+    /// RFC 3986.  The base should have a scheme.
+    pub fn resolve(&self, reference : &Uri) -> String {
+        let base = Parts::of(self);
+        let r = Parts::of(reference);
+
+        let target = if r.scheme.is_some() {
+            Parts { path: remove_dot_segments(&r.path), ..r }
+        }
+        else if r.authority.is_some() {
+            Parts { scheme: base.scheme, path: remove_dot_segments(&r.path), ..r }
+        }
+        else if r.path.is_empty() {
+            Parts { scheme: base.scheme, authority: base.authority, path: base.path, query: r.query.or(base.query), fragment: r.fragment }
+        }
+        else {
+            let path = if r.path.starts_with('/') {
+                r.path
+            }
+            else if base.authority.is_some() && base.path.is_empty() {
+                format!("/{}", r.path)
+            }
+            else {
+                let directory = base.path.rfind('/').map_or("", |i| &base.path[..= i]);
+                format!("{}{}", directory, r.path)
+            };
+            Parts { scheme: base.scheme, authority: base.authority, path: remove_dot_segments(&path), query: r.query, fragment: r.fragment }
+        };
+
+        target.recompose()
+    }
+}
+
+// the components as text, for resolving and recomposing
+struct Parts {
+    scheme : Option<String>,
+    authority : Option<String>,
+    path : String,
+    query : Option<String>,
+    fragment : Option<String>,
+}
+
+impl Parts {
+    fn of(uri : &Uri) -> Parts {
+        let text = |s : &Option<PSym>| s.as_ref().map(|s| s.value.clone());
+        Parts { scheme: text(&uri.scheme)
+              , authority: uri.authority.as_ref().map(|a| a.to_string())
+              , path: uri.path.value.clone()
+              , query: text(&uri.query)
+              , fragment: text(&uri.fragment)
+              }
+    }
+
+    fn recompose(&self) -> String {
+        let mut out = String::new();
+        if let Some(scheme) = &self.scheme {
+            out.push_str(scheme);
+            out.push(':');
+        }
+        if let Some(authority) = &self.authority {
+            out.push_str("//");
+            out.push_str(authority);
+        }
+        out.push_str(&self.path);
+        if let Some(query) = &self.query {
+            out.push('?');
+            out.push_str(query);
+        }
+        if let Some(fragment) = &self.fragment {
+            out.push('#');
+            out.push_str(fragment);
+        }
+        out
+    }
+}
+
+// section 5.2.4 of RFC 3986
+fn remove_dot_segments(path : &str) -> String {
+    let mut input = path;
+    let mut output : Vec<&str> = vec![];
+    while !input.is_empty() {
+        if let Some(rest) = input.strip_prefix("../").or_else(|| input.strip_prefix("./")) {
+            input = rest;
+        }
+        else if input.starts_with("/./") {
+            input = &input[2 ..];
+        }
+        else if input == "/." {
+            input = "/";
+        }
+        else if input.starts_with("/../") || input == "/.." {
+            input = if input == "/.." { "/" } else { &input[3 ..] };
+            output.pop();
+        }
+        else if input == "." || input == ".." {
+            input = "";
+        }
+        else {
+            // move the first segment, with its leading `/`, to the output
+            let end = input[1 ..].find('/').map_or(input.len(), |i| i + 1);
+            output.push(&input[.. end]);
+            input = &input[end ..];
+        }
+    }
+    output.concat()
+}
+
+impl<'a> Input<'a> {
+
+    /// Parses a URI reference as described by RFC 3986: an absolute URI such as
+    /// `http://user@host:80/path?query#fragment` or a relative reference such as
+    /// `../a?b`.  Stops at the first character that can't be part of a URI.
+    pub fn parse_uri(&mut self) -> Result<Uri, ParseError> {
+        let scheme = scheme(self);
+
+        let authority = match self.data {
+            [(_, '/'), (_, '/'), rest @ ..] => {
+                self.data = rest;
+                Some(authority(self)?)
+            },
+            _ => None,
+        };
+
+        let path = component(self, |c| is_pchar(c) || c == '/')?;
+        if scheme.is_none() && authority.is_none() {
+            // otherwise the first segment would read as a scheme
+            let first = path.value.split('/').next().unwrap();
+            if let Some(colon) = first.find(':') {
+                return Err(ParseError::ErrorAt(path.start + colon, "Encountered : in the first segment of a relative path".to_string()));
+            }
+        }
+        if authority.is_some() && !path.value.is_empty() && !path.value.starts_with('/') {
+            return Err(ParseError::ErrorAt(path.start, "Expected path after authority to start with /".to_string()));
+        }
+
+        let query = match self.data {
+            [(_, '?'), rest @ ..] => {
+                self.data = rest;
+                Some(component(self, |c| is_pchar(c) || c == '/' || c == '?')?)
+            },
+            _ => None,
+        };
+        let fragment = match self.data {
+            [(_, '#'), rest @ ..] => {
+                self.data = rest;
+                Some(component(self, |c| is_pchar(c) || c == '/' || c == '?')?)
+            },
+            _ => None,
+        };
+
+        Ok(Uri { scheme, authority, path, query, fragment })
+    }
+}
+
+fn is_unreserved(c : char) -> bool {
+    c.is_ascii_alphanumeric() || "-._~".contains(c)
+}
+
+fn is_sub_delim(c : char) -> bool {
+    "!$&'()*+,;=".contains(c)
+}
+
+fn is_pchar(c : char) -> bool {
+    is_unreserved(c) || is_sub_delim(c) || c == ':' || c == '@'
+}
+
+// the scheme and its `:`, or nothing when the input doesn't start with one
+fn scheme(input : &mut Input) -> Option<PSym> {
+    let len = match input.data {
+        [(_, c), ..] if c.is_ascii_alphabetic() =>
+            input.data.iter().take_while(|(_, c)| c.is_ascii_alphanumeric() || "+-.".contains(*c)).count(),
+        _ => return None,
+    };
+    match input.data.get(len) {
+        Some((_, ':')) => {
+            let (start, end) = (input.data[0].0, input.data[len - 1].0);
+            let value = input.data[.. len].iter().map(|(_, c)| c).collect();
+            input.data = &input.data[len + 1 ..];
+            Some(PSym { start, end, value })
+        },
+        _ => None,
+    }
+}
+
+// characters allowed by `allowed` and percent-encoded octets
+fn component(input : &mut Input, allowed : fn(char) -> bool) -> Result<PSym, ParseError> {
+    let start = input.position();
+    let mut end = start;
+    let mut value = String::new();
+    loop {
+        match input.data {
+            [(_, '%'), (_, h), (i, l), rest @ ..] if h.is_ascii_hexdigit() && l.is_ascii_hexdigit() => {
+                value.push('%');
+                value.push(*h);
+                value.push(*l);
+                end = *i;
+                input.data = rest;
+            },
+            [(i, '%'), ..] => return Err(ParseError::ErrorAt(*i, "Expected two hex digits after %".to_string())),
+            [(i, c), rest @ ..] if allowed(*c) => {
+                value.push(*c);
+                end = *i;
+                input.data = rest;
+            },
+            _ => return Ok(PSym { start, end, value }),
+        }
+    }
+}
+
+fn authority(input : &mut Input) -> Result<Authority, ParseError> {
+    let has_userinfo = input.data.iter()
+                                 .take_while(|(_, c)| !"/?#".contains(*c) && (is_pchar(*c) || *c == '%' || *c == '[' || *c == ']'))
+                                 .any(|(_, c)| *c == '@');
+    let userinfo = if has_userinfo {
+        let userinfo = component(input, |c| is_unreserved(c) || is_sub_delim(c) || c == ':')?;
+        match input.data {
+            [(_, '@'), rest @ ..] => input.data = rest,
+            _ => return Err(input.unexpected("`@`")),
+        }
+        Some(userinfo)
+    }
+    else {
+        None
+    };
+
+    let (host, host_kind) = match input.data {
+        [(_, '['), ..] => ip_literal(input)?,
+        _ => {
+            let host = component(input, |c| is_unreserved(c) || is_sub_delim(c))?;
+            let kind = ipv4(&host.value).map_or(HostKind::RegName, HostKind::Ipv4);
+            (host, kind)
+        },
+    };
+
+    let port = match input.data {
+        [(_, ':'), rest @ ..] => {
+            input.data = rest;
+            Some(component(input, |c| c.is_ascii_digit())?)
+        },
+        _ => None,
+    };
+
+    Ok(Authority { userinfo, host, host_kind, port })
+}
+
+// a dotted quad of decimal octets without leading zeros
+fn ipv4(s : &str) -> Option<[u8; 4]> {
+    let mut octets = [0; 4];
+    let mut parts = s.split('.');
+    for octet in octets.iter_mut() {
+        let part = parts.next()?;
+        let valid = !part.is_empty() && part.len() <= 3 && part.bytes().all(|b| b.is_ascii_digit()) && (part == "0" || !part.starts_with('0'));
+        if !valid {
+            return None;
+        }
+        *octet = part.parse().ok()?;
+    }
+    match parts.next() {
+        None => Some(octets),
+        Some(_) => None,
+    }
+}
+
+fn ipv6(s : &str) -> Option<[u16; 8]> {
+    // pieces on each side of `::`, where the last piece overall may be an IPv4 address
+    fn pieces(s : &str, last : bool) -> Option<Vec<u16>> {
+        if s.is_empty() {
+            return Some(vec![]);
+        }
+        let groups = s.split(':').collect::<Vec<_>>();
+        let mut out = vec![];
+        for (index, group) in groups.iter().enumerate() {
+            if last && index == groups.len() - 1 && group.contains('.') {
+                let [a, b, c, d] = ipv4(group)?;
+                out.push(u16::from_be_bytes([a, b]));
+                out.push(u16::from_be_bytes([c, d]));
+            }
+            else if !group.is_empty() && group.len() <= 4 && group.bytes().all(|b| b.is_ascii_hexdigit()) {
+                out.push(u16::from_str_radix(group, 16).ok()?);
+            }
+            else {
+                return None;
+            }
+        }
+        Some(out)
+    }
+
+    let mut address = [0; 8];
+    match s.find("::") {
+        Some(i) => {
+            let (left, right) = (pieces(&s[.. i], false)?, pieces(&s[i + 2 ..], true)?);
+            if left.len() + right.len() > 7 || s[i + 2 ..].contains("::") {
+                return None;
+            }
+            address[.. left.len()].copy_from_slice(&left);
+            address[8 - right.len() ..].copy_from_slice(&right);
+        },
+        None => {
+            let all = pieces(s, true)?;
+            if all.len() != 8 {
+                return None;
+            }
+            address.copy_from_slice(&all);
+        },
+    }
+    Some(address)
+}
+
+fn ip_literal(input : &mut Input) -> Result<(PSym, HostKind), ParseError> {
+    let start = input.position();
+    input.data = &input.data[1 ..];
+    let len = input.data.iter().take_while(|(_, c)| *c != ']' && (is_unreserved(*c) || is_sub_delim(*c) || *c == ':')).count();
+    let inside = input.data[.. len].iter().map(|(_, c)| c).collect::<String>();
+    input.data = &input.data[len ..];
+    let end = match input.data {
+        [(end, ']'), rest @ ..] => {
+            input.data = rest;
+            *end
+        },
+        _ => return Err(input.unexpected("`]`")),
+    };
+
+    let future = inside.strip_prefix(['v', 'V'])
+                       .and_then(|rest| rest.split_once('.'))
+                       .is_some_and(|(version, text)| !version.is_empty() && version.bytes().all(|b| b.is_ascii_hexdigit()) && !text.is_empty());
+    let kind = if future {
+        HostKind::IpvFuture
+    }
+    else {
+        match ipv6(&inside) {
+            Some(address) => HostKind::Ipv6(address),
+            None => return Err(ParseError::ErrorAt(start + 1, format!("Encountered invalid IPv6 address {}", inside))),
+        }
+    };
+    Ok((PSym { start, end, value: format!("[{}]", inside) }, kind))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s : &str) -> Result<Uri, ParseError> {
+        let data = s.char_indices().collect::<Vec<(usize, char)>>();
+        let mut input = Input::new(&data);
+        let uri = input.parse_uri()?;
+        input.expect_end()?;
+        Ok(uri)
+    }
+
+    #[test]
+    fn should_parse_components() -> Result<(), ParseError> {
+        let uri = parse("https://us%65r:pw@example.com:8080/a/b%20c?x=1&y=/?#frag")?;
+        assert_eq!( uri.scheme.as_ref().unwrap().value, "https" );
+        let authority = uri.authority.as_ref().unwrap();
+        assert_eq!( percent_decode(&authority.userinfo.as_ref().unwrap().value).unwrap(), "user:pw" );
+        assert_eq!( (authority.host.start, authority.host.end), (18, 28) );
+        assert_eq!( authority.host_kind, HostKind::RegName );
+        assert_eq!( authority.port_number(), Some(8080) );
+        assert_eq!( percent_decode(&uri.path.value).unwrap(), "/a/b c" );
+        assert_eq!( (uri.path.start, uri.path.end), (34, 41) );
+        assert_eq!( uri.query.as_ref().unwrap().value, "x=1&y=/?" );
+        assert_eq!( uri.fragment.as_ref().unwrap().value, "frag" );
+        assert_eq!( uri.to_string(), "https://us%65r:pw@example.com:8080/a/b%20c?x=1&y=/?#frag" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_hosts() -> Result<(), ParseError> {
+        let host = |s : &str| parse(s).map(|u| u.authority.unwrap().host_kind);
+        assert_eq!( host("http://192.168.0.1/")?, HostKind::Ipv4([192, 168, 0, 1]) );
+        assert_eq!( host("http://192.168.0.01/")?, HostKind::RegName );
+        assert_eq!( host("http://[::1]:80")?, HostKind::Ipv6([0, 0, 0, 0, 0, 0, 0, 1]) );
+        assert_eq!( host("ldap://[2001:db8::7]/c=GB")?, HostKind::Ipv6([0x2001, 0xdb8, 0, 0, 0, 0, 0, 7]) );
+        assert_eq!( host("http://[::ffff:10.0.0.1]")?, HostKind::Ipv6([0, 0, 0, 0, 0, 0xffff, 0x0a00, 1]) );
+        assert_eq!( host("http://[v7.abc]")?, HostKind::IpvFuture );
+        assert_eq!( host("file:///etc/hosts")?, HostKind::RegName );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_without_authority() -> Result<(), ParseError> {
+        let mail = parse("mailto:John.Doe@example.com")?;
+        assert!( mail.authority.is_none() );
+        assert_eq!( mail.path.value, "John.Doe@example.com" );
+
+        let relative = parse("../a:b/c?q")?;
+        assert!( relative.scheme.is_none() );
+        assert_eq!( relative.path.value, "../a:b/c" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_bad_uris() {
+        assert!( matches!( parse("http://a/%2"), Err(ParseError::ErrorAt(9, _)) ) );
+        assert!( matches!( parse("http://[1::2::3]/"), Err(ParseError::ErrorAt(8, _)) ) );
+        assert!( matches!( parse("http://[::1"), Err(ParseError::EndOfFile(_)) ) );
+        assert!( matches!( parse("1a:b"), Err(ParseError::ErrorAt(2, _)) ) );
+        assert!( matches!( parse("http://a b"), Err(ParseError::ErrorAt(9, _)) ) );
+    }
+
+    #[test]
+    fn should_resolve_references() -> Result<(), ParseError> {
+        // the examples from section 5.4 of RFC 3986
+        let base = parse("http://a/b/c/d;p?q")?;
+        let cases = [ ("g:h", "g:h"), ("g", "http://a/b/c/g"), ("./g", "http://a/b/c/g"), ("g/", "http://a/b/c/g/")
+                    , ("/g", "http://a/g"), ("//g", "http://g"), ("?y", "http://a/b/c/d;p?y"), ("g?y", "http://a/b/c/g?y")
+                    , ("#s", "http://a/b/c/d;p?q#s"), ("g#s", "http://a/b/c/g#s"), ("g?y#s", "http://a/b/c/g?y#s")
+                    , (";x", "http://a/b/c/;x"), ("g;x", "http://a/b/c/g;x"), ("g;x?y#s", "http://a/b/c/g;x?y#s")
+                    , ("", "http://a/b/c/d;p?q"), (".", "http://a/b/c/"), ("./", "http://a/b/c/"), ("..", "http://a/b/")
+                    , ("../", "http://a/b/"), ("../g", "http://a/b/g"), ("../..", "http://a/"), ("../../", "http://a/")
+                    , ("../../g", "http://a/g"), ("../../../g", "http://a/g"), ("../../../../g", "http://a/g")
+                    , ("/./g", "http://a/g"), ("/../g", "http://a/g"), ("g.", "http://a/b/c/g."), (".g", "http://a/b/c/.g")
+                    , ("g..", "http://a/b/c/g.."), ("..g", "http://a/b/c/..g"), ("./../g", "http://a/b/g"), ("./g/.", "http://a/b/c/g/")
+                    , ("g/./h", "http://a/b/c/g/h"), ("g/../h", "http://a/b/c/h"), ("g;x=1/./y", "http://a/b/c/g;x=1/y")
+                    , ("g;x=1/../y", "http://a/b/c/y"), ("g?y/./x", "http://a/b/c/g?y/./x"), ("g?y/../x", "http://a/b/c/g?y/../x")
+                    , ("g#s/./x", "http://a/b/c/g#s/./x"), ("g#s/../x", "http://a/b/c/g#s/../x"), ("http:g", "http:g")
+                    ];
+        for (reference, expected) in cases.iter() {
+            assert_eq!( base.resolve(&parse(reference)?), *expected, "resolving {}", reference );
+        }
+        Ok(())
+    }
+}